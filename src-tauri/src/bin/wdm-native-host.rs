@@ -0,0 +1,3 @@
+fn main() -> std::process::ExitCode {
+    wdm_lib::run_native_host()
+}
@@ -0,0 +1,484 @@
+use crate::downloader::{http_client, probe_url, run_download, DownloadContext, EventSink};
+use crate::persistence::{ChunkRecord, DownloadHistory, DownloadRecord, DownloadStatus};
+use crate::sites::is_video_url;
+use crate::state::{DownloadComplete, DownloadHandle, DownloadProgress, Settings};
+use futures::future::join_all;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const USAGE: &str = "\
+Usage: wdm-cli <command> [options]
+
+Commands:
+  add <url>...              Download one or more URLs
+  resume [id]...            Resume interrupted downloads, all of them if no id is given
+  list                      Show the download history
+
+Options for add:
+  -o, --output <dir>        Folder to save into (default: the download folder from settings)
+  -c, --connections <n>     Connections per download, 1-32 (default: from settings)
+      --name <file>         File name to save as, with a single URL only
+
+Exit codes:
+  0    every download finished
+  1    a download failed or was not found
+  2    invalid arguments
+  130  interrupted; progress is kept and `resume` carries on
+";
+
+const EXIT_OK: u8 = 0;
+const EXIT_FAILED: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// How often progress is redrawn on a terminal
+const TTY_REFRESH: Duration = Duration::from_millis(200);
+
+/// How often a progress line is printed when output goes to a log, e.g. in CI
+const LOG_REFRESH: Duration = Duration::from_secs(5);
+
+enum Command {
+    Add {
+        urls: Vec<String>,
+        output: Option<PathBuf>,
+        connections: Option<u64>,
+        name: Option<String>,
+    },
+    Resume {
+        ids: Vec<String>,
+    },
+    List,
+    Help,
+    Version,
+}
+
+pub fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("wdm-cli: {}\n\n{}", e, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("wdm-cli: failed to start runtime: {}", e);
+            return ExitCode::from(EXIT_FAILED);
+        }
+    };
+    ExitCode::from(runtime.block_on(execute(command)))
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Help);
+    };
+
+    match command.as_str() {
+        "add" => {
+            let mut urls = Vec::new();
+            let mut output = None;
+            let mut connections = None;
+            let mut name = None;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                let mut value = || rest.next().cloned().ok_or(format!("{} needs a value", arg));
+                match arg.as_str() {
+                    "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                    "-c" | "--connections" => {
+                        let n: u64 = value()?.parse().map_err(|_| "--connections must be a number")?;
+                        if !(1..=32).contains(&n) {
+                            return Err("Connections must be between 1 and 32".to_string());
+                        }
+                        connections = Some(n);
+                    }
+                    "--name" => name = Some(value()?),
+                    flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+                    url => urls.push(url.to_string()),
+                }
+            }
+            if urls.is_empty() {
+                return Err("add needs at least one URL".to_string());
+            }
+            if name.is_some() && urls.len() > 1 {
+                return Err("--name can only be used with a single URL".to_string());
+            }
+            Ok(Command::Add { urls, output, connections, name })
+        }
+        "resume" => Ok(Command::Resume { ids: rest.to_vec() }),
+        "list" if rest.is_empty() => Ok(Command::List),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        "-V" | "--version" => Ok(Command::Version),
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+async fn execute(command: Command) -> u8 {
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            return EXIT_OK;
+        }
+        Command::Version => {
+            println!("wdm-cli {}", env!("CARGO_PKG_VERSION"));
+            return EXIT_OK;
+        }
+        _ => {}
+    }
+
+    let settings = Settings::load().await;
+    let history = match DownloadHistory::load().await {
+        Ok(history) => Arc::new(RwLock::new(history)),
+        Err(e) => {
+            eprintln!("wdm-cli: {}", e);
+            return EXIT_FAILED;
+        }
+    };
+
+    let code = match command {
+        Command::Add { urls, output, connections, name } => {
+            add(&settings, &history, urls, output, connections, name).await
+        }
+        Command::Resume { ids } => resume(&settings, &history, ids).await,
+        Command::List => list(&history).await,
+        Command::Help | Command::Version => EXIT_OK,
+    };
+
+    if let Err(e) = history.read().await.flush().await {
+        eprintln!("wdm-cli: failed to save download history: {}", e);
+        return EXIT_FAILED;
+    }
+    code
+}
+
+async fn add(
+    settings: &Settings,
+    history: &Arc<RwLock<DownloadHistory>>,
+    urls: Vec<String>,
+    output: Option<PathBuf>,
+    connections: Option<u64>,
+    name: Option<String>,
+) -> u8 {
+    let dir = output.unwrap_or_else(|| settings.get_download_folder());
+    if !dir.is_dir() {
+        eprintln!("wdm-cli: {} is not a folder", dir.display());
+        return EXIT_USAGE;
+    }
+    let connections = connections.unwrap_or(settings.connections);
+
+    let mut failed = false;
+    let mut taken = HashSet::new();
+    let mut downloads = Vec::new();
+    for url in urls {
+        if is_video_url(&url) {
+            eprintln!("wdm-cli: {}: video sites can only be downloaded in the app", url);
+            failed = true;
+            continue;
+        }
+
+        let info = match http_client(&settings.site_cookies, &url, &[]).await {
+            Ok(client) => probe_url(&client, &url).await,
+            Err(e) => Err(e),
+        };
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("wdm-cli: {}: {}", url, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let filename = free_name(&dir, name.clone().unwrap_or(info.filename), &taken);
+        taken.insert(filename.clone());
+        let record = DownloadRecord::new(
+            format!("{}_{}", filename, chrono::Utc::now().timestamp_millis()),
+            url,
+            filename.clone(),
+            dir.join(&filename).to_string_lossy().to_string(),
+            info.size.unwrap_or(0),
+            info.resumable,
+            connections,
+            false,
+            None,
+        );
+        history.write().await.add_download(record.clone());
+        downloads.push((record, None));
+    }
+
+    let code = run_all(settings, history, downloads).await;
+    if failed && code == EXIT_OK {
+        EXIT_FAILED
+    } else {
+        code
+    }
+}
+
+async fn resume(settings: &Settings, history: &Arc<RwLock<DownloadHistory>>, ids: Vec<String>) -> u8 {
+    let records: Vec<DownloadRecord> = {
+        let history = history.read().await;
+        if ids.is_empty() {
+            history
+                .downloads
+                .values()
+                .filter(|r| is_interrupted(r) && !r.is_playlist)
+                .cloned()
+                .collect()
+        } else {
+            let mut records = Vec::new();
+            for id in &ids {
+                match history.get_download(id) {
+                    Some(record) if is_interrupted(record) => records.push(record.clone()),
+                    Some(_) => {
+                        eprintln!("wdm-cli: {} is not interrupted", id);
+                        return EXIT_FAILED;
+                    }
+                    None => {
+                        eprintln!("wdm-cli: no download with id {}", id);
+                        return EXIT_FAILED;
+                    }
+                }
+            }
+            records
+        }
+    };
+
+    let mut failed = false;
+    let mut downloads = Vec::new();
+    for record in records {
+        if record.is_video || !record.resumable {
+            // Only report what was asked for by id; a bare `resume` just skips them
+            if !ids.is_empty() {
+                let reason = if record.is_video { "is a video, resume it in the app" } else { "does not support resuming" };
+                eprintln!("wdm-cli: {} {}", record.id, reason);
+                failed = true;
+            }
+            continue;
+        }
+        let chunks = record.chunks.clone();
+        downloads.push((record, Some(chunks)));
+    }
+
+    if downloads.is_empty() && !failed {
+        println!("Nothing to resume");
+        return EXIT_OK;
+    }
+
+    let code = run_all(settings, history, downloads).await;
+    if failed && code == EXIT_OK {
+        EXIT_FAILED
+    } else {
+        code
+    }
+}
+
+async fn list(history: &Arc<RwLock<DownloadHistory>>) -> u8 {
+    let history = history.read().await;
+    let mut records: Vec<&DownloadRecord> = history.downloads.values().collect();
+    records.sort_by_key(|r| r.created_at);
+
+    for record in records {
+        let size = if record.total_size > 0 { format_bytes(record.total_size) } else { "-".to_string() };
+        println!("{:<11} {:>10}  {}  {}", record.status.as_str(), size, record.id, record.filename);
+    }
+    EXIT_OK
+}
+
+fn is_interrupted(record: &DownloadRecord) -> bool {
+    matches!(
+        record.status,
+        DownloadStatus::Paused | DownloadStatus::Failed | DownloadStatus::Downloading
+    )
+}
+
+/// Run downloads side by side until they finish or Ctrl+C is pressed
+async fn run_all(
+    settings: &Settings,
+    history: &Arc<RwLock<DownloadHistory>>,
+    downloads: Vec<(DownloadRecord, Option<Vec<ChunkRecord>>)>,
+) -> u8 {
+    if downloads.is_empty() {
+        return EXIT_FAILED;
+    }
+
+    let sink = Arc::new(TerminalSink::new(&downloads));
+    let ctx = DownloadContext {
+        events: sink.clone(),
+        history: Arc::clone(history),
+        site_cookies: settings.site_cookies.clone(),
+    };
+    let handles: Vec<Arc<DownloadHandle>> = downloads
+        .iter()
+        .map(|(record, _)| Arc::new(DownloadHandle::for_record(record, settings.speed_limit)))
+        .collect();
+
+    let renderer = tokio::spawn(Arc::clone(&sink).render());
+    let tasks = downloads.into_iter().zip(handles.iter().cloned()).map(|((record, chunks), handle)| {
+        let ctx = ctx.clone();
+        async move {
+            let id = record.id.clone();
+            (id, run_download(&ctx, handle, record, chunks).await)
+        }
+    });
+
+    tokio::select! {
+        results = join_all(tasks) => {
+            renderer.abort();
+            sink.clear_line();
+            let mut code = EXIT_OK;
+            for (id, result) in results {
+                if let Err(e) = result {
+                    eprintln!("wdm-cli: {} failed: {}", id, e);
+                    code = EXIT_FAILED;
+                }
+            }
+            code
+        }
+        _ = tokio::signal::ctrl_c() => {
+            renderer.abort();
+            sink.clear_line();
+
+            // Save exactly how far each chunk got so `resume` continues from there
+            let mut history = history.write().await;
+            for handle in &handles {
+                handle.paused.store(true, Ordering::SeqCst);
+                for (i, downloaded) in handle.chunk_downloaded.iter().enumerate() {
+                    history.update_chunk_progress(&handle.id, i as u64, downloaded.load(Ordering::Relaxed));
+                }
+                history.update_download(&handle.id, |r| {
+                    if r.status == DownloadStatus::Downloading {
+                        r.status = DownloadStatus::Paused;
+                    }
+                });
+            }
+            eprintln!("Interrupted; run `wdm-cli resume` to continue");
+            EXIT_INTERRUPTED
+        }
+    }
+}
+
+/// A file name that is free on disk and not used by another URL in this run
+fn free_name(dir: &Path, name: String, taken: &HashSet<String>) -> String {
+    let is_free = |candidate: &str| !dir.join(candidate).exists() && !taken.contains(candidate);
+    if is_free(&name) {
+        return name;
+    }
+
+    let path = Path::new(&name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    (1..)
+        .map(|counter| {
+            if ext.is_empty() {
+                format!("{} ({})", stem, counter)
+            } else {
+                format!("{} ({}).{}", stem, counter, ext)
+            }
+        })
+        .find(|candidate| is_free(candidate))
+        .unwrap_or(name)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Shows progress on stderr: a line redrawn in place on a terminal, or a
+/// plain line every few seconds when stderr is a log
+struct TerminalSink {
+    names: BTreeMap<String, String>,
+    active: Mutex<BTreeMap<String, DownloadProgress>>,
+    tty: bool,
+}
+
+impl TerminalSink {
+    fn new(downloads: &[(DownloadRecord, Option<Vec<ChunkRecord>>)]) -> Self {
+        Self {
+            names: downloads
+                .iter()
+                .map(|(r, _)| (r.id.clone(), r.filename.clone()))
+                .collect(),
+            active: Mutex::new(BTreeMap::new()),
+            tty: std::io::stderr().is_terminal(),
+        }
+    }
+
+    async fn render(self: Arc<Self>) {
+        let refresh = if self.tty { TTY_REFRESH } else { LOG_REFRESH };
+        loop {
+            tokio::time::sleep(refresh).await;
+            let line = self.status_line();
+            if line.is_empty() {
+                continue;
+            }
+            if self.tty {
+                eprint!("\r\x1b[K{}", line);
+                let _ = std::io::stderr().flush();
+            } else {
+                eprintln!("{}", line);
+            }
+        }
+    }
+
+    fn status_line(&self) -> String {
+        let Ok(active) = self.active.lock() else {
+            return String::new();
+        };
+        active
+            .values()
+            .map(|p| {
+                let name = self.names.get(&p.id).map(String::as_str).unwrap_or(&p.id);
+                let speed = format!("{}/s", format_bytes(p.speed as u64));
+                if p.total > 0 {
+                    let percent = p.downloaded as f64 / p.total as f64 * 100.0;
+                    format!("{} {:.1}% of {} {}", name, percent, format_bytes(p.total), speed)
+                } else {
+                    format!("{} {} {}", name, format_bytes(p.downloaded), speed)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    fn clear_line(&self) {
+        if self.tty {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+impl EventSink for TerminalSink {
+    fn progress(&self, progress: &DownloadProgress) {
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(progress.id.clone(), progress.clone());
+        }
+    }
+
+    fn complete(&self, complete: &DownloadComplete) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(&complete.id);
+        }
+        self.clear_line();
+        eprintln!("Finished {} ({})", complete.path, format_bytes(complete.total_size));
+    }
+}
@@ -1,66 +1,77 @@
-use crate::downloader::{download_chunked, download_single};
-use crate::persistence::{DownloadRecord, DownloadStatus};
+use crate::cookies::SiteCookies;
+use crate::downloader::{http_client, probe_url, run_download, DownloadContext, EventSink};
+use crate::persistence::{ChunkRecord, DownloadHistory, DownloadRecord, DownloadStatus};
+use crate::export::{self, localize_imported, parse_import, ExportFormat, ImportSource, ImportSummary};
+use crate::ffmpeg::{
+    self, download_ffmpeg, get_ffmpeg_version, is_ffmpeg_available, FfmpegInfo, FfmpegSource,
+};
+use crate::formats::{FormatPreference, FormatPreview};
+use crate::logs::{get_log_path, remove_logs};
+use crate::query::HistoryQuery;
+use crate::rpc::{self, generate_secret, RpcSettings};
+use crate::sites::{self, is_playlist_url, is_video_url, probe_video_url, VideoDetectionSettings};
 use crate::state::{
-    AppState, DownloadError, DownloadHandle, DownloadInfo, FileExistsInfo, UrlInfo,
+    AppState, DownloadComplete, DownloadError, DownloadHandle, DownloadInfo, DownloadProgress,
+    FileExistsInfo, HistoryPageInfo, UrlInfo, YtdlpUpdateCheck, YtdlpUpdateSettings,
 };
-use crate::utils::{extract_filename_from_url, generate_unique_filename};
+use crate::thumbnails::{cache_thumbnail, remove_thumbnail};
+use crate::utils::{extract_filename_from_url, generate_unique_filename, sanitize_filename};
 use crate::video::{
-    download_video, fetch_video_info, is_video_url, VideoDownloadHandle, VideoInfo,
+    download_video, fetch_playlist_info, fetch_video_info, resolve_formats,
+    fill_playlist_fields, validate_output_template, EmbedOptions, PlaylistEntry, PlaylistInfo, VideoDownloadHandle,
+    VideoDownloadRequest, VideoInfo, VideoMetadata, VideoOptions,
+};
+use crate::ytdlp::{
+    self, ensure_ytdlp, get_latest_ytdlp_version, get_ytdlp_version, is_ytdlp_installed,
+    YtdlpChannel, YtdlpUpdate,
 };
-use crate::ytdlp::{ensure_ytdlp, get_ytdlp_version, is_ytdlp_installed};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager};
 use std::process::Command;
 
-#[tauri::command]
-pub async fn fetch_url_info(url: String) -> Result<UrlInfo, String> {
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create client: {}", e))?;
-
-    let response = client
-        .head(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+/// How often the automatic yt-dlp update task wakes up to see if a check is due
+const YTDLP_UPDATE_TICK: tokio::time::Duration = tokio::time::Duration::from_secs(3600);
 
-    if !response.status().is_success() {
-        return Err(format!("HTTP error: {}", response.status()));
+impl EventSink for AppHandle {
+    fn progress(&self, progress: &DownloadProgress) {
+        rpc::record_progress(progress);
+        let _ = self.emit("download-progress", progress);
     }
 
-    let final_url = response.url().to_string();
-    let headers = response.headers();
+    fn complete(&self, complete: &DownloadComplete) {
+        rpc::record_complete(complete);
+        let _ = self.emit("download-complete", complete);
+    }
 
-    let size = headers
-        .get(reqwest::header::CONTENT_LENGTH)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.parse().ok());
+    fn event(&self, name: &str, payload: serde_json::Value) {
+        let _ = self.emit(name, payload);
+    }
+}
 
-    let resumable = headers
-        .get(reqwest::header::ACCEPT_RANGES)
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v == "bytes")
-        .unwrap_or(false);
+/// Run the download engine against the app's history, reporting to the frontend
+async fn download_context(app: &AppHandle) -> DownloadContext {
+    let state = app.state::<AppState>();
+    let site_cookies = state.settings.read().await.site_cookies.clone();
+    DownloadContext {
+        events: Arc::new(app.clone()),
+        history: Arc::clone(&state.history),
+        site_cookies,
+    }
+}
 
-    let filename = headers
-        .get(reqwest::header::CONTENT_DISPOSITION)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|v| {
-            v.split("filename=").nth(1).map(|s| s.trim_matches('"').to_string())
-        })
-        .or_else(|| extract_filename_from_url(&final_url))
-        .or_else(|| extract_filename_from_url(&url))
-        .unwrap_or_else(|| "download".to_string());
+/// HTTP client for `url`, sending cookies from the matching site's cookies.txt
+async fn http_client_for(app: &AppHandle, url: &str) -> Result<reqwest::Client, String> {
+    let state = app.state::<AppState>();
+    let site_cookies = state.settings.read().await.site_cookies.clone();
+    http_client(&site_cookies, url, &[]).await
+}
 
-    Ok(UrlInfo {
-        url: final_url,
-        filename,
-        size,
-        resumable,
-    })
+#[tauri::command]
+pub async fn fetch_url_info(app: AppHandle, url: String) -> Result<UrlInfo, String> {
+    let client = http_client_for(&app, &url).await?;
+    probe_url(&client, &url).await
 }
 
 #[tauri::command]
@@ -93,22 +104,28 @@ pub async fn get_download_history(app: AppHandle) -> Result<Vec<DownloadInfo>, S
     let downloads: Vec<DownloadInfo> = history
         .get_all_downloads()
         .into_iter()
-        .map(|r| DownloadInfo {
-            id: r.id.clone(),
-            url: r.url.clone(),
-            filename: r.filename.clone(),
-            file_path: r.file_path.clone(),
-            total_size: r.total_size,
-            downloaded: r.total_downloaded(),
-            status: format!("{:?}", r.status),
-            resumable: r.resumable,
-            created_at: r.created_at,
-        })
+        .map(DownloadInfo::from)
         .collect();
 
     Ok(downloads)
 }
 
+#[tauri::command]
+pub async fn query_download_history(
+    app: AppHandle,
+    query: HistoryQuery,
+) -> Result<HistoryPageInfo, String> {
+    let state = app.state::<AppState>();
+    let history = state.history.read().await;
+
+    let page = history.query(&query).await?;
+    Ok(HistoryPageInfo {
+        downloads: page.records.into_iter().map(DownloadInfo::from).collect(),
+        total: page.total,
+        offset: query.offset,
+    })
+}
+
 #[tauri::command]
 pub async fn clear_download_history(app: AppHandle) -> Result<(), String> {
     let state = app.state::<AppState>();
@@ -139,11 +156,12 @@ pub async fn clear_download_history(app: AppHandle) -> Result<(), String> {
                 file_path.extension().and_then(|e| e.to_str()).unwrap_or("")
             ));
             let _ = tokio::fs::remove_file(part_path).await;
+            remove_thumbnail(&id, record.thumbnail.as_deref()).await;
         }
+        remove_logs(&id).await;
         history.remove_download(&id);
     }
 
-    history.save().await?;
     Ok(())
 }
 
@@ -164,13 +182,152 @@ pub async fn remove_from_history(app: AppHandle, id: String) -> Result<(), Strin
             file_path.extension().and_then(|e| e.to_str()).unwrap_or("")
         ));
         let _ = tokio::fs::remove_file(part_path).await;
+        remove_thumbnail(&id, record.thumbnail.as_deref()).await;
+    }
+
+    // Playlist entries and split chapters go with their parent, down to chapters of playlist entries
+    let mut children: Vec<String> = Vec::new();
+    let mut parents = vec![id.clone()];
+    while let Some(parent) = parents.pop() {
+        let found: Vec<String> = history
+            .downloads
+            .values()
+            .filter(|r| r.parent_id.as_deref() == Some(parent.as_str()))
+            .map(|r| r.id.clone())
+            .collect();
+        parents.extend(found.iter().cloned());
+        children.extend(found);
+    }
+    for child in children {
+        let thumbnail = history.downloads.get(&child).and_then(|r| r.thumbnail.clone());
+        remove_thumbnail(&child, thumbnail.as_deref()).await;
+        remove_logs(&child).await;
+        history.remove_download(&child);
     }
 
+    remove_logs(&id).await;
     history.remove_download(&id);
-    history.save().await?;
     Ok(())
 }
 
+#[tauri::command]
+pub async fn export_history(
+    app: AppHandle,
+    path: String,
+    format: ExportFormat,
+    include_chunks: bool,
+    unfinished_only: bool,
+) -> Result<usize, String> {
+    let state = app.state::<AppState>();
+    let (content, count) = {
+        let history = state.history.read().await;
+        export::export_history(&history, format, include_chunks, unfinished_only)?
+    };
+
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write export file: {}", e))?;
+    Ok(count)
+}
+
+/// Import a history export, or a plain text/CSV list of URLs to queue
+#[tauri::command]
+pub async fn import_history(app: AppHandle, path: String) -> Result<ImportSummary, String> {
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read import file: {}", e))?;
+
+    let state = app.state::<AppState>();
+    let (entries, invalid) = match parse_import(&content)? {
+        ImportSource::History(records) => {
+            // Drop duplicates before looking at chunk files, which may belong to a local download
+            let total = records.len();
+            let mut records: Vec<DownloadRecord> = {
+                let history = state.history.read().await;
+                records.into_iter().filter(|r| !history.is_known(r)).collect()
+            };
+            let duplicates = total - records.len();
+
+            let download_dir = state.settings.read().await.get_download_folder();
+            localize_imported(&mut records, &download_dir).await;
+            let mut history = state.history.write().await;
+            let mut summary = history.import_records(records);
+            summary.duplicates += duplicates;
+            return Ok(summary);
+        }
+        ImportSource::UrlList { entries, invalid } => (entries, invalid),
+    };
+
+    let settings = state.settings.read().await;
+    let num_connections = settings.connections;
+    let download_dir = settings.get_download_folder();
+    drop(settings);
+
+    let mut summary = ImportSummary {
+        invalid,
+        ..Default::default()
+    };
+    let timestamp = chrono::Utc::now().timestamp_millis();
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        if state.history.read().await.is_duplicate(&entry.url, "") {
+            summary.duplicates += 1;
+            continue;
+        }
+
+        let record = if is_video_url(&entry.url) {
+            DownloadRecord::new(
+                format!("video_{}_{}", timestamp, i),
+                entry.url,
+                "Video Download".to_string(),
+                format!("{}/%(title)s.%(ext)s", download_dir.to_string_lossy()),
+                0,
+                false,
+                1,
+                true,
+                None,
+            )
+        } else {
+            // Look the file up now so the queued record has a name and size
+            let info = fetch_url_info(app.clone(), entry.url.clone()).await.ok();
+            let filename = entry
+                .filename
+                .or_else(|| info.as_ref().map(|i| i.filename.clone()))
+                .or_else(|| extract_filename_from_url(&entry.url))
+                .unwrap_or_else(|| "download".to_string());
+            let filename = if download_dir.join(&filename).exists() {
+                generate_unique_filename(&download_dir, &filename)
+            } else {
+                filename
+            };
+            let file_path = download_dir.join(&filename).to_string_lossy().to_string();
+
+            if state.history.read().await.is_duplicate(&entry.url, &file_path) {
+                summary.duplicates += 1;
+                continue;
+            }
+
+            DownloadRecord::new(
+                format!("{}_{}_{}", filename, timestamp, i),
+                entry.url,
+                filename,
+                file_path,
+                info.as_ref().and_then(|i| i.size).unwrap_or(0),
+                info.as_ref().map(|i| i.resumable).unwrap_or(false),
+                num_connections,
+                false,
+                None,
+            )
+        };
+
+        summary.queued.push(record.id.clone());
+        summary.imported += 1;
+        state.history.write().await.add_download(record);
+    }
+
+    Ok(summary)
+}
+
 #[tauri::command]
 pub async fn start_download(
     app: AppHandle,
@@ -178,18 +335,34 @@ pub async fn start_download(
     filename: String,
     size: u64,
     resumable: bool,
+) -> Result<String, String> {
+    let download_dir = {
+        let state = app.state::<AppState>();
+        let settings = state.settings.read().await;
+        settings.get_download_folder()
+    };
+    begin_download(&app, url, download_dir, filename, size, resumable, Vec::new()).await
+}
+
+/// Add a file download saving into `download_dir` to history and start it.
+/// `headers` are extra "Name: value" request headers kept on the record.
+pub(crate) async fn begin_download(
+    app: &AppHandle,
+    url: String,
+    download_dir: PathBuf,
+    filename: String,
+    size: u64,
+    resumable: bool,
+    headers: Vec<String>,
 ) -> Result<String, String> {
     let state = app.state::<AppState>();
-    let settings = state.settings.read().await;
-    let num_connections = settings.connections;
-    let download_dir = settings.get_download_folder();
-    drop(settings);
+    let num_connections = state.settings.read().await.connections;
 
     let file_path = download_dir.join(&filename);
     let download_id = format!("{}_{}", filename, chrono::Utc::now().timestamp_millis());
 
     // Create and save download record
-    let record = DownloadRecord::new(
+    let mut record = DownloadRecord::new(
         download_id.clone(),
         url.clone(),
         filename.clone(),
@@ -200,90 +373,83 @@ pub async fn start_download(
         false, // is_video
         None,  // thumbnail
     );
+    record.headers = headers;
 
     {
         let mut history = state.history.write().await;
-        history.add_download(record);
-        history.save().await?;
+        history.add_download(record.clone());
+    }
+
+    launch_download(app, record, None).await;
+
+    Ok(download_id)
+}
+
+/// Start a download that was queued without being started, e.g. by an import
+#[tauri::command]
+pub async fn start_queued_download(app: AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let record = {
+        let history = state.history.read().await;
+        history.get_download(&id).cloned()
+    };
+
+    let record = record.ok_or("Download not found in history")?;
+    if record.status != DownloadStatus::Pending {
+        return Err("Download is not queued".to_string());
+    }
+    if state.downloads.read().await.contains_key(&id)
+        || state.video_downloads.read().await.contains_key(&id)
+    {
+        return Err("Download is already running".to_string());
+    }
+
+    if record.is_video {
+        let options = video_options_for(&record);
+        launch_video_download(&app, record.id, record.url, options).await?;
+    } else {
+        launch_download(&app, record, None).await;
     }
+    Ok(())
+}
+
+/// Register a handle for a history record and run its download in the background.
+/// `existing_chunks` continues a chunked download from its saved progress.
+async fn launch_download(
+    app: &AppHandle,
+    record: DownloadRecord,
+    existing_chunks: Option<Vec<ChunkRecord>>,
+) {
+    let state = app.state::<AppState>();
+    let id = record.id.clone();
 
-    // Create download handle
     let speed_limit = {
         let settings = state.settings.read().await;
         settings.speed_limit
     };
-    let handle = Arc::new(DownloadHandle {
-        id: download_id.clone(),
-        cancelled: AtomicBool::new(false),
-        paused: AtomicBool::new(false),
-        chunk_downloaded: (0..num_connections)
-            .map(|_| Arc::new(AtomicU64::new(0)))
-            .collect(),
-        speed_limit: AtomicU64::new(speed_limit),
-    });
+    // Create download handle with existing progress
+    let handle = Arc::new(DownloadHandle::for_record(&record, speed_limit));
 
     {
         let mut downloads = state.downloads.write().await;
-        downloads.insert(download_id.clone(), Arc::clone(&handle));
+        downloads.insert(id.clone(), Arc::clone(&handle));
     }
 
+    let ctx = download_context(app).await;
     let app_clone = app.clone();
-    let download_id_clone = download_id.clone();
 
     tokio::spawn(async move {
-        // Update status to downloading
-        {
-            let state = app_clone.state::<AppState>();
-            let mut history = state.history.write().await;
-            history.update_download(&download_id_clone, |r| {
-                r.status = DownloadStatus::Downloading;
-            });
-            let _ = history.save().await;
-        }
-
-        let result = if resumable && size > 0 {
-            download_chunked(app_clone.clone(), handle, url, file_path, size, num_connections, None).await
-        } else {
-            download_single(app_clone.clone(), handle, url, file_path).await
-        };
+        let result = run_download(&ctx, handle, record, existing_chunks).await;
 
         let state = app_clone.state::<AppState>();
-        let mut downloads = state.downloads.write().await;
-        downloads.remove(&download_id_clone);
-        drop(downloads);
-
-        // Update history based on result
-        let mut history = state.history.write().await;
-        match &result {
-            Ok(_) => {
-                history.update_download(&download_id_clone, |r| {
-                    r.status = DownloadStatus::Completed;
-                });
-            }
-            Err(e) if e.contains("cancelled") => {
-                history.update_download(&download_id_clone, |r| {
-                    r.status = DownloadStatus::Cancelled;
-                });
-            }
-            Err(_) => {
-                history.update_download(&download_id_clone, |r| {
-                    r.status = DownloadStatus::Failed;
-                });
-            }
-        }
-        let _ = history.save().await;
+        state.downloads.write().await.remove(&id);
 
         if let Err(e) = result {
             if !e.contains("cancelled") {
-                let _ = app_clone.emit("download-error", DownloadError {
-                    id: download_id_clone,
-                    error: e,
-                });
+                let _ = app_clone.emit("download-error", DownloadError { id, error: e });
             }
         }
     });
-
-    Ok(download_id)
 }
 
 #[tauri::command]
@@ -305,98 +471,33 @@ pub async fn resume_interrupted_download(app: AppHandle, id: String) -> Result<(
         return Err("Download cannot be resumed".to_string());
     }
 
-    if !record.resumable {
-        return Err("This download does not support resuming".to_string());
-    }
-
-    let num_connections = record.num_connections;
-    let file_path = PathBuf::from(&record.file_path);
+    // A playlist carries on with the entries that haven't finished yet
+    if record.is_playlist {
+        let children = unfinished_playlist_entries(&app, &id).await;
+        if children.is_empty() {
+            return Err("Playlist has nothing left to download".to_string());
+        }
+        let video_downloads = state.video_downloads.read().await;
+        if children.iter().any(|c| video_downloads.contains_key(&c.id)) {
+            return Err("Playlist is already running".to_string());
+        }
+        drop(video_downloads);
 
-    // Create download handle with existing progress
-    let chunk_downloaded: Vec<Arc<AtomicU64>> = record
-        .chunks
-        .iter()
-        .map(|c| Arc::new(AtomicU64::new(c.downloaded)))
-        .collect();
+        tokio::spawn(run_playlist(app.clone(), id, children));
+        return Ok(());
+    }
 
-    let speed_limit = {
-        let settings = state.settings.read().await;
-        settings.speed_limit
-    };
-    let handle = Arc::new(DownloadHandle {
-        id: id.clone(),
-        cancelled: AtomicBool::new(false),
-        paused: AtomicBool::new(false),
-        chunk_downloaded,
-        speed_limit: AtomicU64::new(speed_limit),
-    });
+    if record.is_video {
+        let options = video_options_for(&record);
+        return launch_video_download(&app, record.id, record.url, options).await;
+    }
 
-    {
-        let mut downloads = state.downloads.write().await;
-        downloads.insert(id.clone(), Arc::clone(&handle));
+    if !record.resumable {
+        return Err("This download does not support resuming".to_string());
     }
 
-    let app_clone = app.clone();
-    let id_clone = id.clone();
-    let url = record.url.clone();
-    let total_size = record.total_size;
     let chunks = record.chunks.clone();
-
-    tokio::spawn(async move {
-        {
-            let state = app_clone.state::<AppState>();
-            let mut history = state.history.write().await;
-            history.update_download(&id_clone, |r| {
-                r.status = DownloadStatus::Downloading;
-            });
-            let _ = history.save().await;
-        }
-
-        let result = download_chunked(
-            app_clone.clone(),
-            handle,
-            url,
-            file_path,
-            total_size,
-            num_connections,
-            Some(chunks),
-        )
-        .await;
-
-        let state = app_clone.state::<AppState>();
-        let mut downloads = state.downloads.write().await;
-        downloads.remove(&id_clone);
-        drop(downloads);
-
-        let mut history = state.history.write().await;
-        match &result {
-            Ok(_) => {
-                history.update_download(&id_clone, |r| {
-                    r.status = DownloadStatus::Completed;
-                });
-            }
-            Err(e) if e.contains("cancelled") => {
-                history.update_download(&id_clone, |r| {
-                    r.status = DownloadStatus::Cancelled;
-                });
-            }
-            Err(_) => {
-                history.update_download(&id_clone, |r| {
-                    r.status = DownloadStatus::Failed;
-                });
-            }
-        }
-        let _ = history.save().await;
-
-        if let Err(e) = result {
-            if !e.contains("cancelled") {
-                let _ = app_clone.emit("download-error", DownloadError {
-                    id: id_clone,
-                    error: e,
-                });
-            }
-        }
-    });
+    launch_download(&app, record, Some(chunks)).await;
 
     Ok(())
 }
@@ -432,7 +533,6 @@ pub async fn pause_download(app: AppHandle, id: String) -> Result<(), String> {
     history.update_download(&id, |r| {
         r.status = DownloadStatus::Paused;
     });
-    history.save().await?;
 
     Ok(())
 }
@@ -454,7 +554,6 @@ pub async fn resume_download(app: AppHandle, id: String) -> Result<(), String> {
     history.update_download(&id, |r| {
         r.status = DownloadStatus::Downloading;
     });
-    history.save().await?;
 
     Ok(())
 }
@@ -565,6 +664,20 @@ pub async fn open_file(path: String) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn open_download_log(app: AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if !state.history.read().await.downloads.contains_key(&id) {
+        return Err("Download not found".to_string());
+    }
+
+    let path = get_log_path(&id);
+    if !path.is_file() {
+        return Err("No log for this download".to_string());
+    }
+    open_file(path.to_string_lossy().to_string()).await
+}
+
 #[tauri::command]
 pub async fn show_in_folder(path: String) -> Result<(), String> {
     #[cfg(target_os = "windows")]
@@ -598,156 +711,980 @@ pub async fn show_in_folder(path: String) -> Result<(), String> {
 // Video Download Commands
 // ============================================================================
 
-/// Check if a URL is a video site URL
+/// Check if a URL is a video site URL, asking yt-dlp when probing is enabled
 #[tauri::command]
-pub fn check_video_url(url: String) -> bool {
-    is_video_url(&url)
+pub async fn check_video_url(app: AppHandle, url: String) -> bool {
+    if is_video_url(&url) {
+        return true;
+    }
+
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await.clone();
+    settings.video_detection.probe_ytdlp
+        && probe_video_url(&settings.ytdlp_command, &url, &settings.ytdlp_args(&url)).await
 }
 
 /// Check if yt-dlp is installed
 #[tauri::command]
-pub fn check_ytdlp_installed() -> bool {
-    is_ytdlp_installed()
+pub async fn check_ytdlp_installed(app: AppHandle) -> bool {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    is_ytdlp_installed(&settings.ytdlp_command)
+}
+
+fn ytdlp_progress_callback(app: &AppHandle) -> impl Fn(u64, u64) + Send + 'static {
+    let app = app.clone();
+    move |downloaded: u64, total: u64| {
+        let _ = app.emit(
+            "ytdlp-download-progress",
+            serde_json::json!({
+                "downloaded": downloaded,
+                "total": total
+            }),
+        );
+    }
 }
 
 /// Ensure yt-dlp is installed (download if needed)
 #[tauri::command]
 pub async fn install_ytdlp(app: AppHandle) -> Result<String, String> {
-    let progress_callback = {
-        let app = app.clone();
-        move |downloaded: u64, total: u64| {
-            let _ = app.emit(
-                "ytdlp-download-progress",
-                serde_json::json!({
-                    "downloaded": downloaded,
-                    "total": total
-                }),
-            );
-        }
-    };
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let release = settings.ytdlp_update.release()?;
+    let command = settings.ytdlp_command.clone();
+    drop(settings);
 
-    let path = ensure_ytdlp(progress_callback).await?;
+    let path = ensure_ytdlp(&command, &release, ytdlp_progress_callback(&app)).await?;
     Ok(path.to_string_lossy().to_string())
 }
 
-/// Get yt-dlp version
+/// Update yt-dlp to the latest (or pinned) version
 #[tauri::command]
-pub async fn get_ytdlp_ver() -> Result<String, String> {
-    get_ytdlp_version().await
-}
+pub async fn update_ytdlp(app: AppHandle) -> Result<YtdlpUpdate, String> {
+    let state = app.state::<AppState>();
+    if !state.video_downloads.read().await.is_empty() {
+        return Err("Cannot update yt-dlp while video downloads are running".to_string());
+    }
+    if !state.settings.read().await.ytdlp_command.is_empty() {
+        return Err("A custom yt-dlp command is set; update it outside WDM".to_string());
+    }
 
-/// Fetch video information from URL
-#[tauri::command]
-pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
-    fetch_video_info(&url).await
+    let release = state.settings.read().await.ytdlp_update.release()?;
+    let result = ytdlp::update_ytdlp(&release, ytdlp_progress_callback(&app)).await?;
+    if result.updated {
+        sites::clear_probe_cache();
+    }
+
+    let mut settings = state.settings.write().await;
+    settings.ytdlp_update.last_check = chrono::Utc::now().timestamp();
+    settings.save().await?;
+
+    Ok(result)
 }
 
-/// Start a video download
+/// Compare the installed yt-dlp with the latest release on the configured channel
 #[tauri::command]
-pub async fn start_video_download(
-    app: AppHandle,
-    url: String,
-    format_id: String,
-) -> Result<String, String> {
+pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtdlpUpdateCheck, String> {
     let state = app.state::<AppState>();
     let settings = state.settings.read().await;
-    let download_dir = settings.get_download_folder();
-    let connections = settings.connections;
-    let speed_limit = settings.speed_limit;
+    let update_settings = settings.ytdlp_update.clone();
+    let command = settings.ytdlp_command.clone();
     drop(settings);
 
-    let download_id = format!("video_{}", chrono::Utc::now().timestamp_millis());
-    let download_dir_str = download_dir.to_string_lossy().to_string();
-
-    // Create download handle
-    let handle = Arc::new(VideoDownloadHandle::new(download_id.clone()));
-
-    // Create history record for video
-    // Note: We might want to fetch title first or update it later. 
-    // For now use ID or generic name. The frontend has the title, we could pass it or update it later.
-    // Ideally the `get_video_info` result should be cached or passed back. 
-    // Since we don't have it here easily without re-fetching or changing API, 
-    // let's assume we update it during download progress if possible or just use a placeholder.
-    // However, better UX: use "Video Download" and update later.
-    let record = crate::persistence::DownloadRecord::new(
-        download_id.clone(),
-        url.clone(),
-        "Video Download".to_string(), // Placeholder, should be updated when download starts
-        format!("{}/%(title)s.%(ext)s", download_dir_str), // Template path
-        0, // Unknown size initially
-        false, // Not resumable in same way
-        1, // 1 'connection' logic for our tracking
-        true, // is_video
-        None, // thumbnail - could pass if we change API
-    );
+    let release = update_settings.release()?;
+    let current = get_ytdlp_version(&command).await.ok();
+    let latest = match release.version {
+        Some(version) => version,
+        None => get_latest_ytdlp_version(release.channel).await?,
+    };
 
-    {
-        let mut history = state.history.write().await;
-        history.add_download(record);
-        history.save().await?;
+    Ok(YtdlpUpdateCheck {
+        update_available: current.as_deref() != Some(latest.as_str()),
+        current,
+        latest,
+    })
+}
+
+#[tauri::command]
+pub async fn get_ytdlp_update_settings(app: AppHandle) -> Result<YtdlpUpdateSettings, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.ytdlp_update.clone())
+}
+
+#[tauri::command]
+pub async fn set_ytdlp_update_settings(
+    app: AppHandle,
+    channel: YtdlpChannel,
+    pinned_version: Option<String>,
+    auto_update: bool,
+    interval_hours: u64,
+) -> Result<(), String> {
+    if interval_hours < 1 {
+        return Err("Update interval must be at least 1 hour".to_string());
     }
 
-    {
-        let mut video_downloads = state.video_downloads.write().await;
-        video_downloads.insert(download_id.clone(), Arc::clone(&handle));
+    let pinned_version = pinned_version
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    if let Some(version) = &pinned_version {
+        ytdlp::validate_version(version)?;
     }
 
-    let app_clone = app.clone();
-    let download_id_clone = download_id.clone();
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.ytdlp_update.channel = channel;
+    settings.ytdlp_update.pinned_version = pinned_version;
+    settings.ytdlp_update.auto_update = auto_update;
+    settings.ytdlp_update.interval_hours = interval_hours;
+    settings.save().await?;
+    Ok(())
+}
 
-    // Use connections setting for concurrent fragments (capped at 16 for yt-dlp)
-    let concurrent_fragments = (connections as u32).min(16);
+/// Background task that updates yt-dlp when automatic updates are due
+pub async fn run_ytdlp_auto_update(app: AppHandle) {
+    loop {
+        auto_update_ytdlp(&app).await;
+        tokio::time::sleep(YTDLP_UPDATE_TICK).await;
+    }
+}
 
-    tokio::spawn(async move {
-        let result = download_video(
-            app_clone.clone(),
-            download_id_clone.clone(),
-            url,
-            format_id,
-            download_dir.to_string_lossy().to_string(),
-            handle,
-            concurrent_fragments,
-            speed_limit,
-        )
-        .await;
+async fn auto_update_ytdlp(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let update_settings = settings.ytdlp_update.clone();
+    let custom_command = !settings.ytdlp_command.is_empty();
+    drop(settings);
+    let now = chrono::Utc::now().timestamp();
+    // Don't swap the binary out from under a running download, and leave
+    // user-provided commands alone
+    if custom_command
+        || !is_ytdlp_installed(&[])
+        || !update_settings.is_check_due(now)
+        || !state.video_downloads.read().await.is_empty()
+    {
+        return;
+    }
 
-        let state = app_clone.state::<AppState>();
-        let mut video_downloads = state.video_downloads.write().await;
-        video_downloads.remove(&download_id_clone);
+    let result = match update_settings.release() {
+        Ok(release) => ytdlp::update_ytdlp(&release, |_, _| {}).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(result) if result.updated => {
+            sites::clear_probe_cache();
+            let _ = app.emit("ytdlp-updated", &result);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Automatic yt-dlp update failed: {}", e),
+    }
 
-        if let Err(e) = result {
-            if !e.contains("cancelled") {
-                let _ = app_clone.emit(
-                    "video-error",
-                    serde_json::json!({
-                        "id": download_id_clone,
-                        "error": e
-                    }),
-                );
-            }
+    let mut settings = state.settings.write().await;
+    settings.ytdlp_update.last_check = now;
+    if let Err(e) = settings.save().await {
+        eprintln!("{}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn get_ytdlp_command(app: AppHandle) -> Result<Vec<String>, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.ytdlp_command.clone())
+}
+
+/// Run yt-dlp with a custom command, e.g. `["python3", "-m", "yt_dlp"]`, or
+/// an empty list for the managed binary. Returns the custom command's version.
+#[tauri::command]
+pub async fn set_ytdlp_command(app: AppHandle, command: Vec<String>) -> Result<Option<String>, String> {
+    let command: Vec<String> = command
+        .iter()
+        .map(|arg| arg.trim().to_string())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    let version = if command.is_empty() {
+        None
+    } else {
+        if !is_ytdlp_installed(&command) {
+            return Err(format!("Program not found: {}", command[0]));
+        }
+        Some(get_ytdlp_version(&command).await?)
+    };
+
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.ytdlp_command = command;
+    settings.save().await?;
+    sites::clear_probe_cache();
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn get_ytdlp_extra_args(app: AppHandle) -> Result<Vec<String>, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.ytdlp_extra_args.clone())
+}
+
+/// Set arguments added to every yt-dlp run, one argument per entry
+#[tauri::command]
+pub async fn set_ytdlp_extra_args(app: AppHandle, args: Vec<String>) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.ytdlp_extra_args = args.into_iter().filter(|a| !a.trim().is_empty()).collect();
+    settings.save().await?;
+    sites::clear_probe_cache();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_output_template(app: AppHandle) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.output_template.clone())
+}
+
+/// Set the yt-dlp output template for video downloads, e.g. `%(uploader)s/%(title)s.%(ext)s`
+#[tauri::command]
+pub async fn set_output_template(app: AppHandle, template: String) -> Result<(), String> {
+    let template = template.trim().to_string();
+    validate_output_template(&template)?;
+
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.output_template = template;
+    settings.save().await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_video_detection(app: AppHandle) -> Result<VideoDetectionSettings, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.video_detection.clone())
+}
+
+/// Set extra video URL patterns and whether unknown URLs are checked with yt-dlp
+#[tauri::command]
+pub async fn set_video_detection(app: AppHandle, detection: VideoDetectionSettings) -> Result<(), String> {
+    let patterns: Vec<String> = detection
+        .patterns
+        .iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    sites::set_user_patterns(&patterns)?;
+    sites::clear_probe_cache();
+
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.video_detection = VideoDetectionSettings { patterns, ..detection };
+    settings.save().await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_embed_options(app: AppHandle) -> Result<EmbedOptions, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.embed.clone())
+}
+
+/// Set what new video downloads embed or write by default
+#[tauri::command]
+pub async fn set_embed_options(app: AppHandle, embed: EmbedOptions) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.embed = embed;
+    settings.save().await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_rpc_settings(app: AppHandle) -> Result<RpcSettings, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.rpc.clone())
+}
+
+/// Change the JSON-RPC server settings and restart it. An empty secret gets a
+/// random one, which is returned so it can be shown to the user.
+#[tauri::command]
+pub async fn set_rpc_settings(app: AppHandle, mut rpc: RpcSettings) -> Result<RpcSettings, String> {
+    if rpc.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    rpc.secret = rpc.secret.trim().to_string();
+    if rpc.enabled && rpc.secret.is_empty() {
+        rpc.secret = generate_secret()?;
+    }
+
+    let state = app.state::<AppState>();
+    let previous = state.settings.read().await.rpc.clone();
+    if let Err(e) = apply_rpc_settings(&app, &rpc).await {
+        let _ = apply_rpc_settings(&app, &previous).await;
+        return Err(e);
+    }
+
+    let mut settings = state.settings.write().await;
+    settings.rpc = rpc.clone();
+    settings.save().await?;
+    Ok(rpc)
+}
+
+/// Start the JSON-RPC server at launch if it is enabled
+pub async fn run_rpc_server(app: AppHandle) {
+    let rpc = {
+        let state = app.state::<AppState>();
+        let settings = state.settings.read().await;
+        settings.rpc.clone()
+    };
+    if let Err(e) = apply_rpc_settings(&app, &rpc).await {
+        eprintln!("JSON-RPC server not started: {}", e);
+    }
+}
+
+/// Stop the running server, if any, then start one for `rpc` when enabled
+async fn apply_rpc_settings(app: &AppHandle, rpc: &RpcSettings) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut server = state.rpc_server.write().await;
+    if let Some(running) = server.take() {
+        running.abort();
+        // Wait for the listener to close so its port can be bound again
+        let _ = running.await;
+    }
+    if rpc.enabled {
+        *server = Some(rpc::start_server(app.clone(), rpc.clone()).await?);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_site_cookies(app: AppHandle) -> Result<Vec<SiteCookies>, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    Ok(settings.site_cookies.clone())
+}
+
+/// Replace the per-site cookie sources used for yt-dlp and HTTP downloads
+#[tauri::command]
+pub async fn set_site_cookies(app: AppHandle, sites: Vec<SiteCookies>) -> Result<(), String> {
+    for site in &sites {
+        site.validate()?;
+    }
+
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.site_cookies = sites;
+    settings.save().await?;
+    Ok(())
+}
+
+/// Get yt-dlp version
+#[tauri::command]
+pub async fn get_ytdlp_ver(app: AppHandle) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let command = state.settings.read().await.ytdlp_command.clone();
+    get_ytdlp_version(&command).await
+}
+
+/// Check if ffmpeg is available for merging and audio extraction
+#[tauri::command]
+pub async fn check_ffmpeg_installed(app: AppHandle) -> bool {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    is_ffmpeg_available(settings.ffmpeg_path.as_deref())
+}
+
+/// Get the version, location and capabilities of the ffmpeg in use
+#[tauri::command]
+pub async fn get_ffmpeg_info(app: AppHandle) -> Result<FfmpegInfo, String> {
+    let state = app.state::<AppState>();
+    let ffmpeg_path = state.settings.read().await.ffmpeg_path.clone();
+    ffmpeg::get_ffmpeg_info(ffmpeg_path.as_deref()).await
+}
+
+/// Download a managed ffmpeg build next to yt-dlp
+#[tauri::command]
+pub async fn install_ffmpeg(app: AppHandle) -> Result<String, String> {
+    let progress_callback = {
+        let app = app.clone();
+        move |downloaded: u64, total: u64| {
+            let _ = app.emit(
+                "ffmpeg-download-progress",
+                serde_json::json!({
+                    "downloaded": downloaded,
+                    "total": total
+                }),
+            );
+        }
+    };
+
+    let path = download_ffmpeg(progress_callback).await?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Use a specific ffmpeg binary (or directory), or `None` to go back to auto-detection
+#[tauri::command]
+pub async fn set_ffmpeg_path(app: AppHandle, path: Option<String>) -> Result<(), String> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    if let Some(custom) = &path {
+        let (binary, _) = ffmpeg::find_ffmpeg(Some(custom))
+            .filter(|(_, source)| *source == FfmpegSource::Custom)
+            .ok_or("ffmpeg not found at that location")?;
+        get_ffmpeg_version(&binary).await?;
+    }
+
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.write().await;
+    settings.ffmpeg_path = path;
+    settings.save().await?;
+    Ok(())
+}
+
+/// Fetch video information from URL
+#[tauri::command]
+pub async fn get_video_info(app: AppHandle, url: String) -> Result<VideoInfo, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let (command, extra_args) = (settings.ytdlp_command.clone(), settings.ytdlp_args(&url));
+    drop(settings);
+    fetch_video_info(&command, &url, &extra_args).await
+}
+
+/// Show the yt-dlp arguments a format preference turns into and the formats they pick for `url`
+#[tauri::command]
+pub async fn preview_format_selection(
+    app: AppHandle,
+    url: String,
+    preference: FormatPreference,
+) -> Result<FormatPreview, String> {
+    let selector = preference.to_selector()?;
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let (command, extra_args) = (settings.ytdlp_command.clone(), settings.ytdlp_args(&url));
+    drop(settings);
+    let formats = resolve_formats(&command, &url, &selector, &extra_args).await?;
+    Ok(FormatPreview { selector, formats })
+}
+
+/// Start a video download
+#[tauri::command]
+pub async fn start_video_download(
+    app: AppHandle,
+    url: String,
+    format_id: String,
+    request: Option<VideoDownloadRequest>,
+) -> Result<String, String> {
+    let request = request.unwrap_or_default();
+    let selector = request.format_preference.map(|p| p.to_selector()).transpose()?;
+
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let download_dir = settings.get_download_folder();
+    let ffmpeg_path = settings.ffmpeg_path.clone();
+    let template = request
+        .output_template
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| settings.output_template.clone());
+    let extra_args = settings.ytdlp_args(&url);
+    let ytdlp = settings.ytdlp_command.clone();
+    let embed = request.embed.unwrap_or_else(|| settings.embed.clone());
+    drop(settings);
+
+    validate_output_template(&template)?;
+    let audio = request.audio;
+    if audio.is_some() && !is_ffmpeg_available(ffmpeg_path.as_deref()) {
+        return Err("ffmpeg is required for audio extraction but was not found. Install ffmpeg and try again.".to_string());
+    }
+    if embed.needs_ffmpeg() && !is_ffmpeg_available(ffmpeg_path.as_deref()) {
+        return Err("ffmpeg is required to embed metadata, thumbnails or chapters but was not found. Install ffmpeg or turn embedding off.".to_string());
+    }
+    let chapters = request.chapters.filter(|c| !c.is_empty());
+    if let Some(chapters) = &chapters {
+        chapters.validate()?;
+        if !is_ffmpeg_available(ffmpeg_path.as_deref()) {
+            return Err("ffmpeg is required for chapters, sections and SponsorBlock but was not found. Install ffmpeg and try again.".to_string());
         }
+    }
+
+    // Fetch the details again if the frontend didn't pass them along
+    let info = match request.info {
+        Some(info) => Some(info),
+        None => fetch_video_info(&ytdlp, &url, &extra_args).await.ok(),
+    };
+
+    let download_id = format!("video_{}", chrono::Utc::now().timestamp_millis());
+
+    let mut options = VideoOptions::new(format_id, &download_dir, template.trim());
+    if let Some(selector) = selector {
+        options.format_id = selector.format;
+        options.format_sort = selector.sort;
+        options.merge_output_format = selector.merge_output_format;
+    }
+    options.subtitles = request.subtitles.filter(|s| !s.languages.is_empty());
+    if audio.is_some() && options.format_id == "best" {
+        options.format_id = "bestaudio/best".to_string();
+    }
+    options.audio = audio;
+    options.live = request.live;
+    options.chapters = chapters;
+    options.embed = (!embed.is_empty()).then_some(embed);
+    // The title stands in for the filename until yt-dlp reports the real one
+    let mut record = crate::persistence::DownloadRecord::new(
+        download_id.clone(),
+        url.clone(),
+        info.as_ref()
+            .map(|i| sanitize_filename(&i.title))
+            .unwrap_or_else(|| "Video Download".to_string()),
+        options.output_template.clone(), // Template path
+        0, // Unknown size initially
+        true, // Resumed by re-running yt-dlp with --continue
+        1, // 1 'connection' logic for our tracking
+        true, // is_video
+        info.as_ref().and_then(|i| i.thumbnail.clone()), // Replaced by the cached copy below
+    );
+    record.video_options = Some(options.clone());
+    record.video_metadata = info.as_ref().map(|i| VideoMetadata {
+        title: Some(i.title.clone()),
+        uploader: i.uploader.clone(),
+        duration: i.duration,
+        ..Default::default()
     });
+    let thumbnail = record.thumbnail.clone();
+
+    {
+        let mut history = state.history.write().await;
+        history.add_download(record);
+    }
+
+    if let Some(thumbnail) = thumbnail {
+        tokio::spawn(cache_record_thumbnail(app.clone(), download_id.clone(), thumbnail));
+    }
+
+    launch_video_download(&app, download_id.clone(), url, options).await?;
 
     Ok(download_id)
 }
 
+/// Save a video's thumbnail so history can show it offline, then point the record at the copy
+async fn cache_record_thumbnail(app: AppHandle, id: String, url: String) {
+    let result = match http_client_for(&app, &url).await {
+        Ok(client) => cache_thumbnail(&client, &url, &id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(path) => {
+            let state = app.state::<AppState>();
+            let mut history = state.history.write().await;
+            history.update_download(&id, |r| r.thumbnail = Some(path.to_string_lossy().to_string()));
+        }
+        Err(e) => eprintln!("Failed to cache thumbnail for {}: {}", id, e),
+    }
+}
+
+/// Register a handle for a video history record and run yt-dlp in the background
+async fn launch_video_download(
+    app: &AppHandle,
+    download_id: String,
+    url: String,
+    options: VideoOptions,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let handle = register_video_handle(&state, &download_id)
+        .await
+        .ok_or("Download is already running")?;
+    let app = app.clone();
+    tokio::spawn(async move {
+        let _ = run_video_download(app, download_id, url, options, handle).await;
+    });
+    Ok(())
+}
+
+/// Add a handle for `id` unless one is there already. Checking and inserting
+/// under one lock keeps two resumes from both starting yt-dlp.
+async fn register_video_handle(state: &AppState, id: &str) -> Option<Arc<VideoDownloadHandle>> {
+    let mut video_downloads = state.video_downloads.write().await;
+    if video_downloads.contains_key(id) {
+        return None;
+    }
+    let handle = Arc::new(VideoDownloadHandle::new(id.to_string()));
+    video_downloads.insert(id.to_string(), Arc::clone(&handle));
+    Some(handle)
+}
+
+/// Run a video download to completion, reporting errors to the frontend
+async fn run_video_download(
+    app: AppHandle,
+    download_id: String,
+    url: String,
+    options: VideoOptions,
+    handle: Arc<VideoDownloadHandle>,
+) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await.clone();
+
+    let ctx = download_context(&app).await;
+    let result = download_video(&ctx, &settings, download_id.clone(), url, options, handle).await;
+
+    let mut video_downloads = state.video_downloads.write().await;
+    video_downloads.remove(&download_id);
+    drop(video_downloads);
+
+    if let Err(e) = &result {
+        if !e.contains("cancelled") && !e.contains("paused") {
+            let _ = app.emit(
+                "video-error",
+                serde_json::json!({
+                    "id": download_id,
+                    "error": e
+                }),
+            );
+        }
+    }
+
+    result
+}
+
+/// yt-dlp options to (re)run a video record with. Records queued without
+/// options download the best format to their output path.
+fn video_options_for(record: &DownloadRecord) -> VideoOptions {
+    record.video_options.clone().unwrap_or_else(|| VideoOptions {
+        format_id: "best".to_string(),
+        output_template: record.file_path.clone(),
+        subtitles: None,
+        audio: None,
+        format_sort: Vec::new(),
+        merge_output_format: None,
+        live: None,
+        chapters: None,
+        embed: None,
+    })
+}
+
+/// Check if a URL is a playlist or channel
+#[tauri::command]
+pub fn check_playlist_url(url: String) -> bool {
+    is_playlist_url(&url)
+}
+
+/// List the entries of a playlist or channel
+#[tauri::command]
+pub async fn get_playlist_info(app: AppHandle, url: String) -> Result<PlaylistInfo, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let (command, extra_args) = (settings.ytdlp_command.clone(), settings.ytdlp_args(&url));
+    drop(settings);
+    fetch_playlist_info(&command, &url, &extra_args).await
+}
+
+/// Queue the selected playlist entries as video downloads grouped under one
+/// playlist record, and download them one after another
+#[tauri::command]
+pub async fn start_playlist_download(
+    app: AppHandle,
+    url: String,
+    title: String,
+    source_id: Option<String>,
+    entries: Vec<PlaylistEntry>,
+    format_id: String,
+    format_preference: Option<FormatPreference>,
+) -> Result<String, String> {
+    if entries.is_empty() {
+        return Err("No playlist entries selected".to_string());
+    }
+    let selector = format_preference.map(|p| p.to_selector()).transpose()?;
+
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().await;
+    let download_dir = settings.get_download_folder();
+    let template = settings.output_template.clone();
+    let embed = settings.embed.clone();
+    let ffmpeg_path = settings.ffmpeg_path.clone();
+    drop(settings);
+    validate_output_template(&template)?;
+    if embed.needs_ffmpeg() && !is_ffmpeg_available(ffmpeg_path.as_deref()) {
+        return Err("ffmpeg is required to embed metadata, thumbnails or chapters but was not found. Install ffmpeg or turn embedding off.".to_string());
+    }
+
+    // Templates that mention the playlist lay out their own folders
+    let output_dir = if template.contains("%(playlist") {
+        download_dir
+    } else {
+        download_dir.join(sanitize_filename(&title))
+    };
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    let playlist_id = format!("playlist_{}", timestamp);
+    let count = entries.len();
+
+    let mut playlist = DownloadRecord::new(
+        playlist_id.clone(),
+        url,
+        title.clone(),
+        output_dir.to_string_lossy().to_string(),
+        0,
+        false,
+        0,
+        true,
+        None,
+    );
+    playlist.is_playlist = true;
+    playlist.chunks.clear();
+
+    let children: Vec<DownloadRecord> = entries
+        .into_iter()
+        .map(|entry| {
+            let entry_template =
+                fill_playlist_fields(&template, entry.index, count, &title, source_id.as_deref());
+            let mut options = VideoOptions::new(format_id.clone(), &output_dir, &entry_template);
+            if let Some(selector) = &selector {
+                options.format_id = selector.format.clone();
+                options.format_sort = selector.sort.clone();
+                options.merge_output_format = selector.merge_output_format.clone();
+            }
+            options.embed = (!embed.is_empty()).then(|| embed.clone());
+            let mut record = DownloadRecord::new(
+                format!("video_{}_{}", timestamp, entry.index),
+                entry.url,
+                entry.title,
+                options.output_template.clone(),
+                0,
+                true,
+                1,
+                true,
+                None,
+            );
+            record.parent_id = Some(playlist_id.clone());
+            record.video_options = Some(options);
+            record
+        })
+        .collect();
+
+    {
+        let mut history = state.history.write().await;
+        history.add_download(playlist);
+        for child in &children {
+            history.add_download(child.clone());
+        }
+    }
+
+    let app_clone = app.clone();
+    let id = playlist_id.clone();
+    tokio::spawn(async move {
+        run_playlist(app_clone, id, children).await;
+    });
+
+    Ok(playlist_id)
+}
+
+/// Entries of a playlist that still need downloading, in playlist order
+async fn unfinished_playlist_entries(app: &AppHandle, playlist_id: &str) -> Vec<DownloadRecord> {
+    let state = app.state::<AppState>();
+    let history = state.history.read().await;
+    let mut children: Vec<DownloadRecord> = history
+        .downloads
+        .values()
+        .filter(|r| r.parent_id.as_deref() == Some(playlist_id))
+        .filter(|r| !matches!(r.status, DownloadStatus::Completed | DownloadStatus::Cancelled))
+        .cloned()
+        .collect();
+    // Entry ids end in their playlist index
+    children.sort_by_key(|r| {
+        let index = r.id.rsplit('_').next().and_then(|i| i.parse::<u64>().ok());
+        (r.created_at, index)
+    });
+    children
+}
+
+/// Download playlist entries in order, then settle the playlist record's status.
+/// Pausing an entry stops the run; resuming the playlist picks up from there.
+async fn run_playlist(app: AppHandle, playlist_id: String, children: Vec<DownloadRecord>) {
+    let state = app.state::<AppState>();
+    state.history.write().await.update_download(&playlist_id, |r| {
+        r.status = DownloadStatus::Downloading;
+    });
+
+    for child in children {
+        // Skip entries that finished, or were cancelled or removed while queued
+        let still_queued = state
+            .history
+            .read()
+            .await
+            .get_download(&child.id)
+            .map(|r| !matches!(r.status, DownloadStatus::Completed | DownloadStatus::Cancelled))
+            .unwrap_or(false);
+        if !still_queued {
+            continue;
+        }
+
+        // Another run already has this entry
+        let Some(handle) = register_video_handle(&state, &child.id).await else {
+            continue;
+        };
+        let options = video_options_for(&child);
+        let _ = run_video_download(app.clone(), child.id.clone(), child.url, options, handle).await;
+
+        let paused = state
+            .history
+            .read()
+            .await
+            .get_download(&child.id)
+            .is_some_and(|r| r.status == DownloadStatus::Paused);
+        if paused {
+            break;
+        }
+    }
+
+    let mut history = state.history.write().await;
+    settle_playlist_status(&mut history, &playlist_id);
+}
+
+/// Set a playlist record's status from its entries once none is running
+fn settle_playlist_status(history: &mut DownloadHistory, playlist_id: &str) {
+    let statuses: Vec<DownloadStatus> = history
+        .downloads
+        .values()
+        .filter(|r| r.parent_id.as_deref() == Some(playlist_id))
+        .map(|r| r.status.clone())
+        .collect();
+    let finished = |s: &DownloadStatus| matches!(s, DownloadStatus::Completed | DownloadStatus::Cancelled);
+    let status = if statuses.iter().all(|s| *s == DownloadStatus::Cancelled) {
+        DownloadStatus::Cancelled
+    } else if statuses.iter().all(finished) {
+        DownloadStatus::Completed
+    } else if statuses.iter().all(|s| finished(s) || *s == DownloadStatus::Failed) {
+        DownloadStatus::Failed
+    } else {
+        DownloadStatus::Paused
+    };
+    history.update_download(playlist_id, |r| r.status = status);
+}
+
+/// Ids of a playlist's entries
+async fn playlist_entry_ids(state: &AppState, playlist_id: &str) -> Vec<String> {
+    let history = state.history.read().await;
+    history
+        .downloads
+        .values()
+        .filter(|r| r.parent_id.as_deref() == Some(playlist_id))
+        .map(|r| r.id.clone())
+        .collect()
+}
+
 /// Cancel a video download
 #[tauri::command]
 pub async fn cancel_video_download(app: AppHandle, id: String) -> Result<(), String> {
     let state = app.state::<AppState>();
+
+    // Cancelling a playlist cancels every unfinished entry, including the one running now
+    if is_playlist(&state, &id).await {
+        let children = playlist_entry_ids(&state, &id).await;
+        {
+            let mut history = state.history.write().await;
+            for child in &children {
+                history.update_download(child, |r| {
+                    if !matches!(r.status, DownloadStatus::Completed | DownloadStatus::Cancelled) {
+                        r.status = DownloadStatus::Cancelled;
+                    }
+                });
+            }
+        }
+
+        let video_downloads = state.video_downloads.read().await;
+        let mut running = false;
+        for child in &children {
+            if let Some(handle) = video_downloads.get(child) {
+                kill_video_process(handle).await;
+                running = true;
+            }
+        }
+        drop(video_downloads);
+
+        // A running playlist settles its own status when the entry stops
+        if !running {
+            settle_playlist_status(&mut *state.history.write().await, &id);
+        }
+        return Ok(());
+    }
+
     let video_downloads = state.video_downloads.read().await;
 
     if let Some(handle) = video_downloads.get(&id) {
-        handle.cancelled.store(true, Ordering::SeqCst);
+        kill_video_process(handle).await;
+        Ok(())
+    } else {
+        Err("Video download not found".to_string())
+    }
+}
 
-        // Try to kill the process
-        let mut process = handle.process.lock().await;
-        if let Some(child) = process.as_mut() {
-            let _ = child.kill().await;
-        }
+async fn is_playlist(state: &AppState, id: &str) -> bool {
+    let history = state.history.read().await;
+    history.get_download(id).is_some_and(|r| r.is_playlist)
+}
 
+async fn kill_video_process(handle: &VideoDownloadHandle) {
+    handle.cancelled.store(true, Ordering::SeqCst);
+
+    // Try to kill the process
+    handle.kill().await;
+}
+
+/// Stop a live recording and keep what has been recorded so far
+#[tauri::command]
+pub async fn stop_video_recording(app: AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let live = state
+        .history
+        .read()
+        .await
+        .get_download(&id)
+        .and_then(|r| r.video_options.as_ref())
+        .is_some_and(|o| o.live.is_some());
+    // Stopping keeps the file as finished, which only makes sense for a stream
+    if !live {
+        return Err("Only live recordings can be stopped; pause or cancel the download instead".to_string());
+    }
+
+    let video_downloads = state.video_downloads.read().await;
+    let handle = video_downloads.get(&id).ok_or("Video download not found")?;
+    handle.stopping.store(true, Ordering::SeqCst);
+    let result = handle.interrupt().await;
+    if result.is_err() {
+        handle.stopping.store(false, Ordering::SeqCst);
+    }
+    result
+}
+
+/// Pause a video download by stopping yt-dlp; its partial files are kept
+#[tauri::command]
+pub async fn pause_video_download(app: AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+
+    // Pausing a playlist pauses the entry running now, which ends the run
+    let id = if is_playlist(&state, &id).await {
+        let children = playlist_entry_ids(&state, &id).await;
+        let video_downloads = state.video_downloads.read().await;
+        children
+            .into_iter()
+            .find(|child| video_downloads.contains_key(child))
+            .ok_or("Playlist is not downloading")?
+    } else {
+        id
+    };
+
+    let video_downloads = state.video_downloads.read().await;
+
+    if let Some(handle) = video_downloads.get(&id) {
+        handle.paused.store(true, Ordering::SeqCst);
+        handle.kill().await;
         Ok(())
     } else {
         Err("Video download not found".to_string())
     }
+}
+
+/// Resume a paused video download with the same format and output path
+#[tauri::command]
+pub async fn resume_video_download(app: AppHandle, id: String) -> Result<(), String> {
+    resume_interrupted_download(app, id).await
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Browsers yt-dlp can read cookies from
+pub const SUPPORTED_BROWSERS: &[&str] = &[
+    "brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CookieSource {
+    /// A Netscape format cookies.txt file
+    File { path: String },
+    /// Cookies read by yt-dlp straight from a browser profile
+    Browser { browser: String, profile: Option<String> },
+}
+
+/// Cookies to send to a site and its subdomains
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SiteCookies {
+    pub domain: String,
+    pub source: CookieSource,
+}
+
+impl SiteCookies {
+    pub fn validate(&self) -> Result<(), String> {
+        if normalize_domain(&self.domain).is_empty() {
+            return Err("Cookie domain is empty".to_string());
+        }
+        match &self.source {
+            CookieSource::File { path } => {
+                if !Path::new(path).is_file() {
+                    return Err(format!("Cookie file does not exist: {}", path));
+                }
+            }
+            CookieSource::Browser { browser, .. } => {
+                if !SUPPORTED_BROWSERS.contains(&browser.to_lowercase().as_str()) {
+                    return Err(format!("Unsupported browser: {}", browser));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain
+        .trim()
+        .trim_start_matches('.')
+        .trim_start_matches("www.")
+        .to_lowercase()
+}
+
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Pick the most specific cookie entry whose domain covers `url`
+pub fn find_site_cookies<'a>(sites: &'a [SiteCookies], url: &str) -> Option<&'a SiteCookies> {
+    let host = reqwest::Url::parse(url).ok()?.host_str()?.to_lowercase();
+    sites
+        .iter()
+        .filter(|s| host_matches(&host, &normalize_domain(&s.domain)))
+        .max_by_key(|s| normalize_domain(&s.domain).len())
+}
+
+/// yt-dlp arguments to use the site's cookies
+pub fn ytdlp_cookie_args(site: &SiteCookies) -> Vec<String> {
+    match &site.source {
+        CookieSource::File { path } => vec!["--cookies".to_string(), path.clone()],
+        CookieSource::Browser { browser, profile } => {
+            let spec = match profile.as_ref().filter(|p| !p.is_empty()) {
+                Some(profile) => format!("{}:{}", browser.to_lowercase(), profile),
+                None => browser.to_lowercase(),
+            };
+            vec!["--cookies-from-browser".to_string(), spec]
+        }
+    }
+}
+
+/// Build a `Cookie` header for `url` from a site's cookies.txt.
+/// Browser cookie stores are only readable by yt-dlp, so they give `None` here.
+/// The jar is read on every call so cookies exported again by the browser apply.
+pub async fn cookie_header(sites: &[SiteCookies], url: &str) -> Option<String> {
+    let site = find_site_cookies(sites, url)?;
+    let CookieSource::File { path } = &site.source else {
+        return None;
+    };
+
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    let now = chrono::Utc::now().timestamp();
+
+    let pairs: Vec<String> = content
+        .lines()
+        .filter_map(|line| {
+            // HttpOnly cookies are written as comments with this prefix
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+                return None;
+            };
+
+            let domain = domain.trim_start_matches('.').to_lowercase();
+            let domain_ok = if include_subdomains.eq_ignore_ascii_case("TRUE") {
+                host_matches(&host, &domain)
+            } else {
+                host == domain
+            };
+            let secure_ok = !secure.eq_ignore_ascii_case("TRUE") || url.scheme() == "https";
+            let path_ok = url.path().starts_with(path);
+            // 0 marks a session cookie
+            let expires: i64 = expires.parse().unwrap_or(0);
+            let fresh = expires == 0 || expires > now;
+
+            (domain_ok && secure_ok && path_ok && fresh).then(|| format!("{}={}", name, value))
+        })
+        .collect();
+
+    if pairs.is_empty() {
+        None
+    } else {
+        Some(pairs.join("; "))
+    }
+}
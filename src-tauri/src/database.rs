@@ -0,0 +1,386 @@
+use crate::migrations::backup_path;
+use crate::persistence::{ChunkRecord, DownloadRecord, DownloadStatus};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Row, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Schema migrations, indexed by the `user_version` they upgrade from
+const MIGRATIONS: &[&str] = &[
+    // v0 -> v1: initial schema
+    "
+    CREATE TABLE IF NOT EXISTS downloads (
+        id TEXT PRIMARY KEY,
+        url TEXT NOT NULL,
+        filename TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        total_size INTEGER NOT NULL,
+        resumable INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        num_connections INTEGER NOT NULL,
+        is_video INTEGER NOT NULL DEFAULT 0,
+        thumbnail TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS chunks (
+        download_id TEXT NOT NULL REFERENCES downloads(id) ON DELETE CASCADE,
+        id INTEGER NOT NULL,
+        range_start INTEGER NOT NULL,
+        range_end INTEGER NOT NULL,
+        downloaded INTEGER NOT NULL,
+        PRIMARY KEY (download_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_downloads_status ON downloads(status);
+    CREATE INDEX IF NOT EXISTS idx_downloads_created_at ON downloads(created_at);
+    ",
+    // v1 -> v2: playlist grouping
+    "
+    ALTER TABLE downloads ADD COLUMN parent_id TEXT;
+    ALTER TABLE downloads ADD COLUMN is_playlist INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX IF NOT EXISTS idx_downloads_parent_id ON downloads(parent_id);
+    ",
+    // v2 -> v3: yt-dlp options for resuming video downloads, stored as JSON
+    "
+    ALTER TABLE downloads ADD COLUMN video_options TEXT;
+    ",
+    // v3 -> v4: subtitle files of a video, as a JSON array
+    "
+    ALTER TABLE downloads ADD COLUMN subtitle_files TEXT;
+    ",
+    // v4 -> v5: metadata of finished videos, as JSON
+    "
+    ALTER TABLE downloads ADD COLUMN video_metadata TEXT;
+    ",
+    // v5 -> v6: request headers from the browser, as a JSON array
+    "
+    ALTER TABLE downloads ADD COLUMN headers TEXT;
+    ",
+    // v6 -> v7: lowercase URL host for history queries, filled in from `url` on open
+    "
+    ALTER TABLE downloads ADD COLUMN host TEXT;
+    CREATE INDEX IF NOT EXISTS idx_downloads_host ON downloads(host);
+    ",
+];
+
+/// Current version of the history database schema
+pub const SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64;
+
+const RECORD_COLUMNS: &[&str] = &[
+    "id",
+    "url",
+    "filename",
+    "file_path",
+    "total_size",
+    "resumable",
+    "status",
+    "num_connections",
+    "is_video",
+    "thumbnail",
+    "created_at",
+    "updated_at",
+    "parent_id",
+    "is_playlist",
+    "video_options",
+    "subtitle_files",
+    "video_metadata",
+    "headers",
+    "host",
+];
+
+/// A set of changes to write to the database in one transaction
+#[derive(Default)]
+pub struct HistoryBatch {
+    /// Records whose row (and all chunk rows) should be written
+    pub records: Vec<DownloadRecord>,
+    /// Individual chunk progress updates: (download id, chunk, record updated_at)
+    pub chunks: Vec<(String, ChunkRecord, i64)>,
+    pub removed: Vec<String>,
+}
+
+impl HistoryBatch {
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.chunks.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// SQLite-backed store for download history
+pub struct HistoryDb {
+    conn: Mutex<Connection>,
+}
+
+impl HistoryDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open history database: {}", e))?;
+        // secure_delete zeroes removed rows, which may hold session cookies from the browser
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON; PRAGMA secure_delete = ON;")
+            .map_err(|e| format!("Failed to configure history database: {}", e))?;
+        migrate(&conn, path)?;
+        fill_hosts(&conn).map_err(|e| format!("Failed to migrate history database: {}", e))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn load_all(&self) -> Result<HashMap<String, DownloadRecord>, String> {
+        let conn = self.conn.lock().map_err(|_| "History database lock poisoned".to_string())?;
+
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM downloads", RECORD_COLUMNS.join(", ")))
+            .map_err(|e| format!("Failed to query history: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0), record_from_row(row))))
+            .map_err(|e| format!("Failed to query history: {}", e))?;
+
+        // Skip rows that can't be decoded rather than losing the whole history
+        let mut downloads: HashMap<String, DownloadRecord> = HashMap::new();
+        for row in rows {
+            match row {
+                Ok((_, Ok(record))) => {
+                    downloads.insert(record.id.clone(), record);
+                }
+                Ok((id, Err(e))) => eprintln!(
+                    "Skipping history record {}: {}",
+                    id.unwrap_or_else(|_| "with unreadable id".to_string()),
+                    e
+                ),
+                Err(e) => eprintln!("Skipping unreadable history row: {}", e),
+            }
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT download_id, id, range_start, range_end, downloaded FROM chunks ORDER BY download_id, id")
+            .map_err(|e| format!("Failed to query chunks: {}", e))?;
+        let chunks = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    ChunkRecord {
+                        id: row.get(1)?,
+                        start: row.get(2)?,
+                        end: row.get(3)?,
+                        downloaded: row.get(4)?,
+                    },
+                ))
+            })
+            .map_err(|e| format!("Failed to query chunks: {}", e))?;
+
+        for row in chunks {
+            match row {
+                Ok((download_id, chunk)) => {
+                    if let Some(record) = downloads.get_mut(&download_id) {
+                        record.chunks.push(chunk);
+                    }
+                }
+                Err(e) => eprintln!("Skipping unreadable chunk row: {}", e),
+            }
+        }
+
+        Ok(downloads)
+    }
+
+    /// Ids of the records matching `filter` (a WHERE clause or nothing), sorted
+    /// by `order`, one page at a time, and how many records match in all
+    pub fn select_ids(
+        &self,
+        filter: &str,
+        order: &str,
+        params: &[Value],
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<String>, usize), String> {
+        let conn = self.conn.lock().map_err(|_| "History database lock poisoned".to_string())?;
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM downloads{}", filter),
+                params_from_iter(params),
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to query history: {}", e))?;
+
+        let page = [Value::Integer(limit as i64), Value::Integer(offset as i64)];
+        let mut stmt = conn
+            .prepare(&format!("SELECT id FROM downloads{}{} LIMIT ? OFFSET ?", filter, order))
+            .map_err(|e| format!("Failed to query history: {}", e))?;
+        let ids = stmt
+            .query_map(params_from_iter(params.iter().chain(page.iter())), |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+            .map_err(|e| format!("Failed to query history: {}", e))?;
+
+        Ok((ids, total as usize))
+    }
+
+    pub fn apply(&self, batch: &HistoryBatch) -> Result<(), String> {
+        let mut conn = self.conn.lock().map_err(|_| "History database lock poisoned".to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+        for record in &batch.records {
+            write_record(&tx, record).map_err(|e| format!("Failed to write record: {}", e))?;
+        }
+
+        for (download_id, chunk, updated_at) in &batch.chunks {
+            tx.execute(
+                "UPDATE chunks SET downloaded = ?3 WHERE download_id = ?1 AND id = ?2",
+                params![download_id, chunk.id, chunk.downloaded],
+            )
+            .map_err(|e| format!("Failed to update chunk: {}", e))?;
+            tx.execute(
+                "UPDATE downloads SET updated_at = ?2 WHERE id = ?1",
+                params![download_id, updated_at],
+            )
+            .map_err(|e| format!("Failed to update record: {}", e))?;
+        }
+
+        for id in &batch.removed {
+            tx.execute("DELETE FROM downloads WHERE id = ?1", [id])
+                .map_err(|e| format!("Failed to delete record: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit history changes: {}", e))
+    }
+}
+
+fn migrate(conn: &Connection, path: &Path) -> Result<(), String> {
+    let version: u64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    // A newer WDM wrote this database. Keep a copy and carry on with the
+    // columns we know about rather than dropping anything.
+    if version > SCHEMA_VERSION {
+        let backup = backup_path(path, version);
+        eprintln!(
+            "{} has schema version {} but this build only understands {}; keeping a copy at {}",
+            path.display(),
+            version,
+            SCHEMA_VERSION,
+            backup.display()
+        );
+        if !backup.exists() {
+            conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+                .map_err(|e| format!("Failed to back up history database: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    for (from, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            sql,
+            from + 1
+        ))
+        .map_err(|e| format!("Failed to migrate history database to v{}: {}", from + 1, e))?;
+    }
+    Ok(())
+}
+
+/// Lowercase host of `url`, or an empty string when it has none
+fn url_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Fill the `host` column of rows written before it existed
+fn fill_hosts(conn: &Connection) -> rusqlite::Result<()> {
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT id, url FROM downloads WHERE host IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, url) in rows {
+        conn.execute("UPDATE downloads SET host = ?1 WHERE id = ?2", params![url_host(&url), id])?;
+    }
+    Ok(())
+}
+
+/// INSERT for a full record row that updates every column except `created_at` on conflict
+fn upsert_sql() -> String {
+    let placeholders: Vec<String> = (1..=RECORD_COLUMNS.len()).map(|i| format!("?{}", i)).collect();
+    let updates: Vec<String> = RECORD_COLUMNS
+        .iter()
+        .filter(|c| **c != "id" && **c != "created_at")
+        .map(|c| format!("{} = excluded.{}", c, c))
+        .collect();
+    format!(
+        "INSERT INTO downloads ({}) VALUES ({}) ON CONFLICT(id) DO UPDATE SET {}",
+        RECORD_COLUMNS.join(", "),
+        placeholders.join(", "),
+        updates.join(", ")
+    )
+}
+
+fn write_record(tx: &Transaction, record: &DownloadRecord) -> rusqlite::Result<()> {
+    tx.execute(&upsert_sql(), params![
+        record.id,
+        record.url,
+        record.filename,
+        record.file_path,
+        record.total_size,
+        record.resumable,
+        record.status.as_str(),
+        record.num_connections,
+        record.is_video,
+        record.thumbnail,
+        record.created_at,
+        record.updated_at,
+        record.parent_id,
+        record.is_playlist,
+        to_json(&record.video_options),
+        serde_json::to_string(&record.subtitle_files).ok(),
+        to_json(&record.video_metadata),
+        serde_json::to_string(&record.headers).ok(),
+        url_host(&record.url),
+    ])?;
+
+    tx.execute("DELETE FROM chunks WHERE download_id = ?1", [&record.id])?;
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO chunks (download_id, id, range_start, range_end, downloaded) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for chunk in &record.chunks {
+        stmt.execute(params![record.id, chunk.id, chunk.start, chunk.end, chunk.downloaded])?;
+    }
+    Ok(())
+}
+
+fn record_from_row(row: &Row) -> rusqlite::Result<DownloadRecord> {
+    let status: String = row.get(6)?;
+    Ok(DownloadRecord {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        filename: row.get(2)?,
+        file_path: row.get(3)?,
+        total_size: row.get(4)?,
+        resumable: row.get(5)?,
+        status: DownloadStatus::parse(&status).unwrap_or(DownloadStatus::Failed),
+        num_connections: row.get(7)?,
+        chunks: Vec::new(),
+        is_video: row.get(8)?,
+        thumbnail: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        parent_id: row.get(12)?,
+        is_playlist: row.get(13)?,
+        video_options: from_json(row.get(14)?),
+        subtitle_files: from_json(row.get(15)?).unwrap_or_default(),
+        video_metadata: from_json(row.get(16)?),
+        headers: from_json(row.get(17)?).unwrap_or_default(),
+    })
+}
+
+/// Optional structured fields are stored as JSON text
+fn to_json<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value.as_ref().and_then(|v| serde_json::to_string(v).ok())
+}
+
+fn from_json<T: DeserializeOwned>(text: Option<String>) -> Option<T> {
+    text.and_then(|t| serde_json::from_str(&t).ok())
+}
@@ -1,22 +1,163 @@
-use crate::persistence::ChunkRecord;
-use crate::state::{AppState, ChunkProgress, DownloadComplete, DownloadHandle, DownloadProgress};
+use crate::cookies::{cookie_header, SiteCookies};
+use crate::persistence::{ChunkRecord, DownloadHistory, DownloadRecord, DownloadStatus};
+use crate::state::{ChunkProgress, DownloadComplete, DownloadHandle, DownloadProgress, UrlInfo};
+use crate::utils::extract_filename_from_url;
 use futures::stream::StreamExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager};
 use tokio::fs::File;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+/// Receives what the download engine reports: the app forwards it to the
+/// frontend, the CLI draws it on the terminal
+pub trait EventSink: Send + Sync {
+    fn progress(&self, progress: &DownloadProgress);
+    fn complete(&self, complete: &DownloadComplete);
+    /// Other named events, such as yt-dlp's progress, which carries fields the
+    /// file engine doesn't have. Sinks that don't show them ignore them.
+    fn event(&self, _name: &str, _payload: serde_json::Value) {}
+}
+
+/// What a download needs from whoever runs it
+#[derive(Clone)]
+pub struct DownloadContext {
+    pub events: Arc<dyn EventSink>,
+    pub history: Arc<RwLock<DownloadHistory>>,
+    pub site_cookies: Vec<SiteCookies>,
+}
+
+/// HTTP client for `url`, sending cookies from the matching site's cookies.txt.
+/// `extra_headers` are "Name: value" lines, e.g. from the browser that started
+/// the download, and win over the site cookies.
+pub async fn http_client(
+    site_cookies: &[SiteCookies],
+    url: &str,
+    extra_headers: &[String],
+) -> Result<reqwest::Client, String> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let cookies = cookie_header(site_cookies, url).await;
+    if let Some(value) = cookies.and_then(|c| reqwest::header::HeaderValue::from_str(&c).ok()) {
+        headers.insert(reqwest::header::COOKIE, value);
+    }
+    for line in extra_headers {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid header: {}", line))?;
+        let name = reqwest::header::HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| format!("Invalid header {}: {}", name, e))?;
+        let value = reqwest::header::HeaderValue::from_str(value.trim())
+            .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+        headers.insert(name, value);
+    }
+
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))
+}
+
+/// Look up a URL's name, size and range support with a HEAD request
+pub async fn probe_url(client: &reqwest::Client, url: &str) -> Result<UrlInfo, String> {
+    let response = client
+        .head(url)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    let final_url = response.url().to_string();
+    let headers = response.headers();
+
+    let size = headers
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+
+    let resumable = headers
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v == "bytes")
+        .unwrap_or(false);
+
+    let filename = headers
+        .get(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.split("filename=").nth(1).map(|s| s.trim_matches('"').to_string())
+        })
+        .or_else(|| extract_filename_from_url(&final_url))
+        .or_else(|| extract_filename_from_url(url))
+        .unwrap_or_else(|| "download".to_string());
+
+    Ok(UrlInfo {
+        url: final_url,
+        filename,
+        size,
+        resumable,
+    })
+}
+
+/// Run a file download to completion and record the outcome in history.
+/// `existing_chunks` continues a chunked download from its saved progress.
+pub async fn run_download(
+    ctx: &DownloadContext,
+    handle: Arc<DownloadHandle>,
+    record: DownloadRecord,
+    existing_chunks: Option<Vec<ChunkRecord>>,
+) -> Result<String, String> {
+    let id = record.id.clone();
+    ctx.history.write().await.update_download(&id, |r| {
+        r.status = DownloadStatus::Downloading;
+    });
+
+    let result = if existing_chunks.is_some() || (record.resumable && record.total_size > 0) {
+        download_chunked(ctx.clone(), handle, &record, existing_chunks).await
+    } else {
+        download_single(ctx.clone(), handle, &record).await
+    };
+
+    let status = match &result {
+        Ok(_) => DownloadStatus::Completed,
+        Err(e) if e.contains("cancelled") => DownloadStatus::Cancelled,
+        Err(_) => DownloadStatus::Failed,
+    };
+    ctx.history.write().await.update_download(&id, |r| {
+        // Browser headers can carry session cookies; drop them once the download has ended
+        r.headers.clear();
+        r.status = status;
+    });
+
+    result
+}
+
+/// Folder next to the output file that holds its chunks until they are merged
+pub fn chunk_temp_dir(file_path: &Path, download_id: &str) -> PathBuf {
+    file_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(format!(".wdm_temp_{}", download_id))
+}
+
+pub fn chunk_file(temp_dir: &Path, chunk_id: u64) -> PathBuf {
+    temp_dir.join(format!("chunk_{}", chunk_id))
+}
 
 pub async fn download_chunked(
-    app: AppHandle,
+    ctx: DownloadContext,
     handle: Arc<DownloadHandle>,
-    url: String,
-    file_path: PathBuf,
-    total_size: u64,
-    num_connections: u64,
+    record: &DownloadRecord,
     existing_chunks: Option<Vec<ChunkRecord>>,
 ) -> Result<String, String> {
+    let url = record.url.clone();
+    let file_path = PathBuf::from(&record.file_path);
+    let total_size = record.total_size;
+    let num_connections = record.num_connections;
     let chunk_size = total_size / num_connections;
     let download_id = handle.id.clone();
 
@@ -40,22 +181,19 @@ pub async fn download_chunked(
             .collect()
     };
 
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create client: {}", e))?;
+    let client = http_client(&ctx.site_cookies, &url, &record.headers).await?;
 
-    let temp_dir = file_path.parent().unwrap().join(format!(".wdm_temp_{}", download_id));
+    let temp_dir = chunk_temp_dir(&file_path, &download_id);
     tokio::fs::create_dir_all(&temp_dir)
         .await
         .map_err(|e| format!("Failed to create temp directory: {}", e))?;
 
     // Progress reporter
-    let app_clone = app.clone();
+    let events = Arc::clone(&ctx.events);
     let handle_clone = Arc::clone(&handle);
     let chunk_sizes: Vec<u64> = chunks.iter().map(|(_, start, end, _)| end - start + 1).collect();
     let download_id_clone = download_id.clone();
-    let app_for_save = app.clone();
+    let history = Arc::clone(&ctx.history);
     let id_for_save = download_id.clone();
 
     let progress_handle = tokio::spawn(async move {
@@ -101,18 +239,16 @@ pub async fn download_chunked(
                 chunk_progress: chunk_progress.clone(),
             };
 
-            let _ = app_clone.emit("download-progress", &progress);
+            events.progress(&progress);
 
             // Save progress to history every second (10 iterations)
             save_counter += 1;
             if save_counter >= 10 {
                 save_counter = 0;
-                let state = app_for_save.state::<AppState>();
-                let mut history = state.history.write().await;
+                let mut history = history.write().await;
                 for cp in &chunk_progress {
                     history.update_chunk_progress(&id_for_save, cp.id, cp.downloaded);
                 }
-                let _ = history.save().await;
             }
 
             if total_downloaded >= total_size {
@@ -180,7 +316,7 @@ pub async fn download_chunked(
 
     if handle.cancelled.load(Ordering::SeqCst) {
         let _ = tokio::fs::remove_dir_all(&temp_dir).await; // Clean up temp dir
-        let _ = app.emit("download-progress", DownloadProgress {
+        ctx.events.progress(&DownloadProgress {
             id: download_id,
             downloaded: 0,
             total: total_size,
@@ -196,7 +332,7 @@ pub async fn download_chunked(
 
     // Add any pre-existing chunk files
     for i in 0..num_connections {
-        let chunk_path = temp_dir.join(format!("chunk_{}", i));
+        let chunk_path = chunk_file(&temp_dir, i);
         if chunk_path.exists() && !chunk_paths.iter().any(|(id, _)| *id == i) {
             chunk_paths.push((i, chunk_path));
         }
@@ -214,7 +350,7 @@ pub async fn download_chunked(
         filename: file_path.file_name().unwrap().to_string_lossy().to_string(),
         total_size,
     };
-    let _ = app.emit("download-complete", &complete);
+    ctx.events.complete(&complete);
 
     Ok(file_path.to_string_lossy().to_string())
 }
@@ -230,7 +366,7 @@ async fn download_chunk(
     downloaded: Arc<AtomicU64>,
     handle: Arc<DownloadHandle>,
 ) -> Result<PathBuf, String> {
-    let chunk_path = temp_dir.join(format!("chunk_{}", chunk_id));
+    let chunk_path = chunk_file(&temp_dir, chunk_id);
     let actual_start = start + already_downloaded;
 
     if actual_start > end {
@@ -332,16 +468,14 @@ async fn merge_chunks(chunk_paths: &[PathBuf], output_path: &PathBuf) -> Result<
 }
 
 pub async fn download_single(
-    app: AppHandle,
+    ctx: DownloadContext,
     handle: Arc<DownloadHandle>,
-    url: String,
-    file_path: PathBuf,
+    record: &DownloadRecord,
 ) -> Result<String, String> {
     let download_id = handle.id.clone();
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create client: {}", e))?;
+    let url = record.url.clone();
+    let file_path = PathBuf::from(&record.file_path);
+    let client = http_client(&ctx.site_cookies, &url, &record.headers).await?;
 
     let response = client
         .get(&url)
@@ -374,7 +508,7 @@ pub async fn download_single(
         if handle.cancelled.load(Ordering::SeqCst) {
             drop(file);
             let _ = tokio::fs::remove_file(&part_path).await;
-            let _ = app.emit("download-progress", DownloadProgress {
+            ctx.events.progress(&DownloadProgress {
                 id: download_id,
                 downloaded: 0,
                 total: total_size,
@@ -391,7 +525,7 @@ pub async fn download_single(
                 let _ = tokio::fs::remove_file(&part_path).await;
                 return Err("Download cancelled".to_string());
             }
-            let _ = app.emit("download-progress", DownloadProgress {
+            ctx.events.progress(&DownloadProgress {
                 id: download_id.clone(),
                 downloaded,
                 total: total_size,
@@ -427,7 +561,7 @@ pub async fn download_single(
                     total: total_size,
                 }],
             };
-            let _ = app.emit("download-progress", &progress);
+            ctx.events.progress(&progress);
             last_emit = std::time::Instant::now();
         }
     }
@@ -446,7 +580,7 @@ pub async fn download_single(
         filename: file_path.file_name().unwrap().to_string_lossy().to_string(),
         total_size,
     };
-    let _ = app.emit("download-complete", &complete);
+    ctx.events.complete(&complete);
 
     Ok(file_path.to_string_lossy().to_string())
 }
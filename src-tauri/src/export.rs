@@ -0,0 +1,221 @@
+use crate::downloader::{chunk_file, chunk_temp_dir};
+use crate::migrations::{document_version, migrate_history, stamp_version, HISTORY_VERSION};
+use crate::persistence::{DownloadHistory, DownloadRecord, DownloadStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Versioned JSON history document, same layout as the legacy `downloads.json`
+    Json,
+    /// One URL per line
+    UrlList,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryDocument {
+    downloads: HashMap<String, DownloadRecord>,
+}
+
+#[derive(Clone, Serialize, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    /// Ids of imported records that are waiting to be started
+    pub queued: Vec<String>,
+}
+
+/// A URL read from a plain text or CSV list, with an optional filename column
+pub struct ListEntry {
+    pub url: String,
+    pub filename: Option<String>,
+}
+
+pub enum ImportSource {
+    History(Vec<DownloadRecord>),
+    UrlList { entries: Vec<ListEntry>, invalid: usize },
+}
+
+fn is_unfinished(record: &DownloadRecord) -> bool {
+    record.status != DownloadStatus::Completed && record.status != DownloadStatus::Cancelled
+}
+
+/// Fit imported records to this machine. Cached thumbnails stay behind on the
+/// other machine, unfinished file downloads move into `download_dir`, and a
+/// chunk keeps its progress only if its file is here with the recorded size;
+/// otherwise it starts over. Files on disk are never touched.
+pub async fn localize_imported(records: &mut [DownloadRecord], download_dir: &Path) {
+    for record in records.iter_mut() {
+        let remote = record
+            .thumbnail
+            .as_deref()
+            .is_some_and(|t| t.starts_with("http://") || t.starts_with("https://"));
+        if !remote {
+            record.thumbnail = None;
+        }
+    }
+
+    for record in records.iter_mut().filter(|r| !r.is_video && is_unfinished(r)) {
+        if record.filename.is_empty() {
+            continue;
+        }
+        let file_path = download_dir.join(&record.filename);
+        let temp_dir = chunk_temp_dir(&file_path, &record.id);
+        record.file_path = file_path.to_string_lossy().to_string();
+
+        for chunk in record.chunks.iter_mut().filter(|c| c.downloaded > 0) {
+            let path = chunk_file(&temp_dir, chunk.id);
+            let size = tokio::fs::metadata(&path).await.map(|m| m.len()).ok();
+            if size != Some(chunk.downloaded) {
+                chunk.downloaded = 0;
+            }
+        }
+    }
+}
+
+/// Serialize history records for export.
+/// Without `include_chunks`, unfinished records are reset so they start over when imported.
+pub fn export_history(
+    history: &DownloadHistory,
+    format: ExportFormat,
+    include_chunks: bool,
+    unfinished_only: bool,
+) -> Result<(String, usize), String> {
+    let records: Vec<&DownloadRecord> = history
+        .get_all_downloads()
+        .into_iter()
+        .filter(|r| !unfinished_only || is_unfinished(r))
+        .collect();
+    let count = records.len();
+
+    match format {
+        ExportFormat::UrlList => {
+            let mut content: String = records.iter().map(|r| format!("{}\n", r.url)).collect();
+            if content.is_empty() {
+                content.push('\n');
+            }
+            Ok((content, count))
+        }
+        ExportFormat::Json => {
+            let downloads = records
+                .into_iter()
+                .map(|r| {
+                    let mut record = r.clone();
+                    // Headers from the browser may hold session cookies; keep them out of exports
+                    record.headers.clear();
+                    if record.status == DownloadStatus::Downloading {
+                        record.status = DownloadStatus::Paused;
+                    }
+                    if !include_chunks && is_unfinished(&record) {
+                        record.status = DownloadStatus::Pending;
+                        for chunk in record.chunks.iter_mut() {
+                            chunk.downloaded = 0;
+                        }
+                    }
+                    (record.id.clone(), record)
+                })
+                .collect();
+
+            let mut value = serde_json::to_value(HistoryDocument { downloads })
+                .map_err(|e| format!("Failed to serialize history: {}", e))?;
+            stamp_version(&mut value, HISTORY_VERSION);
+            let content = serde_json::to_string_pretty(&value)
+                .map_err(|e| format!("Failed to serialize history: {}", e))?;
+            Ok((content, count))
+        }
+    }
+}
+
+/// Work out whether `content` is a history document or a list of URLs
+pub fn parse_import(content: &str) -> Result<ImportSource, String> {
+    if content.trim_start().starts_with('{') {
+        let mut value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse history file: {}", e))?;
+        if document_version(&value) > HISTORY_VERSION {
+            return Err(format!(
+                "History file was exported by a newer version of WDM (schema {})",
+                document_version(&value)
+            ));
+        }
+        migrate_history(&mut value);
+        let document: HistoryDocument = serde_json::from_value(value)
+            .map_err(|e| format!("Failed to parse history file: {}", e))?;
+        return Ok(ImportSource::History(document.downloads.into_values().collect()));
+    }
+
+    let mut entries = Vec::new();
+    let mut invalid = 0;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Plain lists have one URL per line; CSV rows may add a filename after it
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|f| f.trim().trim_matches('"').trim())
+            .collect();
+        let Some(pos) = fields
+            .iter()
+            .position(|f| f.starts_with("http://") || f.starts_with("https://"))
+        else {
+            // Tolerate a CSV header row
+            if !line.to_lowercase().contains("url") {
+                invalid += 1;
+            }
+            continue;
+        };
+
+        if reqwest::Url::parse(fields[pos]).is_err() {
+            invalid += 1;
+            continue;
+        }
+        entries.push(ListEntry {
+            url: fields[pos].to_string(),
+            filename: fields
+                .get(pos + 1)
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_string()),
+        });
+    }
+
+    Ok(ImportSource::UrlList { entries, invalid })
+}
+
+impl DownloadHistory {
+    /// True if a record already downloads `url`, or writes to `file_path`
+    pub fn is_duplicate(&self, url: &str, file_path: &str) -> bool {
+        self.downloads
+            .values()
+            .any(|r| r.url == url || (!r.is_video && r.file_path == file_path))
+    }
+
+    /// True if an imported record matches one we already have
+    pub fn is_known(&self, record: &DownloadRecord) -> bool {
+        self.downloads.contains_key(&record.id) || self.is_duplicate(&record.url, &record.file_path)
+    }
+
+    /// Add imported history records, skipping ones we already have
+    pub fn import_records(&mut self, records: Vec<DownloadRecord>) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for mut record in records {
+            if self.is_known(&record) {
+                summary.duplicates += 1;
+                continue;
+            }
+            if record.status == DownloadStatus::Downloading {
+                record.status = DownloadStatus::Paused;
+            }
+            if record.status == DownloadStatus::Pending {
+                summary.queued.push(record.id.clone());
+            }
+            self.add_download(record);
+            summary.imported += 1;
+        }
+        summary
+    }
+}
@@ -0,0 +1,313 @@
+use crate::ytdlp::{fetch_checksum, get_ytdlp_dir, verify_sha256};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Static builds published for yt-dlp; these include ffprobe as well
+const FFMPEG_RELEASE_URL: &str = "https://github.com/yt-dlp/FFmpeg-Builds/releases/download/latest";
+
+/// Checksum list published with every FFmpeg-Builds release
+const FFMPEG_CHECKSUMS_ASSET: &str = "checksums.sha256";
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const FFMPEG_ASSET: Option<&str> = Some("ffmpeg-master-latest-linux64-gpl.tar.xz");
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const FFMPEG_ASSET: Option<&str> = Some("ffmpeg-master-latest-linuxarm64-gpl.tar.xz");
+
+#[cfg(target_os = "windows")]
+const FFMPEG_ASSET: Option<&str> = Some("ffmpeg-master-latest-win64-gpl.zip");
+
+// No build with published checksums exists for other platforms, macOS included
+#[cfg(not(any(
+    all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")),
+    target_os = "windows"
+)))]
+const FFMPEG_ASSET: Option<&str> = None;
+
+#[cfg(target_os = "windows")]
+const FFMPEG_BINARY_NAME: &str = "ffmpeg.exe";
+
+#[cfg(not(target_os = "windows"))]
+const FFMPEG_BINARY_NAME: &str = "ffmpeg";
+
+#[cfg(target_os = "windows")]
+const FFPROBE_BINARY_NAME: &str = "ffprobe.exe";
+
+#[cfg(not(target_os = "windows"))]
+const FFPROBE_BINARY_NAME: &str = "ffprobe";
+
+#[derive(Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FfmpegSource {
+    /// Path set by the user in Settings
+    Custom,
+    /// Installed by WDM next to yt-dlp
+    Managed,
+    /// Found on PATH
+    System,
+}
+
+/// Encoders and tools yt-dlp post-processing relies on
+#[derive(Clone, Serialize, Default)]
+pub struct FfmpegCapabilities {
+    pub mp3: bool,
+    pub aac: bool,
+    pub opus: bool,
+    /// ffprobe sits next to ffmpeg (needed for some post-processors)
+    pub ffprobe: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FfmpegInfo {
+    pub path: String,
+    pub source: FfmpegSource,
+    pub version: String,
+    pub capabilities: FfmpegCapabilities,
+}
+
+/// Get the path of the ffmpeg binary installed by WDM
+pub fn get_managed_ffmpeg_path() -> PathBuf {
+    get_ytdlp_dir().join(FFMPEG_BINARY_NAME)
+}
+
+/// Find ffmpeg on PATH
+fn find_on_path() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(FFMPEG_BINARY_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Locate ffmpeg: the custom path from Settings, then the managed copy, then PATH
+pub fn find_ffmpeg(custom_path: Option<&str>) -> Option<(PathBuf, FfmpegSource)> {
+    if let Some(custom) = custom_path.filter(|p| !p.trim().is_empty()) {
+        let custom = PathBuf::from(custom);
+        // A directory is accepted too, as yt-dlp's --ffmpeg-location does
+        let binary = if custom.is_dir() { custom.join(FFMPEG_BINARY_NAME) } else { custom };
+        if binary.is_file() {
+            return Some((binary, FfmpegSource::Custom));
+        }
+    }
+
+    let managed = get_managed_ffmpeg_path();
+    if managed.is_file() {
+        return Some((managed, FfmpegSource::Managed));
+    }
+
+    find_on_path().map(|p| (p, FfmpegSource::System))
+}
+
+/// Check if ffmpeg is available for yt-dlp post-processing
+pub fn is_ffmpeg_available(custom_path: Option<&str>) -> bool {
+    find_ffmpeg(custom_path).is_some()
+}
+
+/// Get the version line reported by an ffmpeg binary
+pub async fn get_ffmpeg_version(path: &Path) -> Result<String, String> {
+    let output = tokio::process::Command::new(path)
+        .args(["-hide_banner", "-version"])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        return Err("Failed to get ffmpeg version".to_string());
+    }
+
+    // "ffmpeg version 6.1.1 Copyright (c) ..."
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next().unwrap_or_default();
+    Ok(first_line
+        .strip_prefix("ffmpeg version ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or(first_line)
+        .to_string())
+}
+
+async fn get_ffmpeg_capabilities(path: &Path) -> FfmpegCapabilities {
+    let encoders = tokio::process::Command::new(path)
+        .args(["-hide_banner", "-encoders"])
+        .output()
+        .await
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+    let has_encoder = |name: &str| encoders.split_whitespace().any(|word| word == name);
+
+    FfmpegCapabilities {
+        mp3: has_encoder("libmp3lame"),
+        aac: has_encoder("aac") || has_encoder("libfdk_aac"),
+        opus: has_encoder("libopus") || has_encoder("opus"),
+        ffprobe: path.with_file_name(FFPROBE_BINARY_NAME).is_file(),
+    }
+}
+
+/// Version, location and capabilities of the ffmpeg WDM will use
+pub async fn get_ffmpeg_info(custom_path: Option<&str>) -> Result<FfmpegInfo, String> {
+    let (path, source) = find_ffmpeg(custom_path).ok_or("ffmpeg not found")?;
+    let version = get_ffmpeg_version(&path).await?;
+    let capabilities = get_ffmpeg_capabilities(&path).await;
+
+    Ok(FfmpegInfo {
+        path: path.to_string_lossy().to_string(),
+        source,
+        version,
+        capabilities,
+    })
+}
+
+/// Download a static ffmpeg build into the yt-dlp bin directory
+pub async fn download_ffmpeg<F>(progress_callback: F) -> Result<PathBuf, String>
+where
+    F: Fn(u64, u64) + Send + 'static,
+{
+    let asset = FFMPEG_ASSET.ok_or(
+        "No verified ffmpeg build is available for this platform. Install ffmpeg (e.g. with Homebrew) or set its path in Settings.",
+    )?;
+    let url = format!("{}/{}", FFMPEG_RELEASE_URL, asset);
+    let bin_dir = get_ytdlp_dir();
+
+    fs::create_dir_all(&bin_dir)
+        .await
+        .map_err(|e| format!("Failed to create bin directory: {}", e))?;
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let expected_hash = fetch_checksum(
+        &client,
+        &format!("{}/{}", FFMPEG_RELEASE_URL, FFMPEG_CHECKSUMS_ASSET),
+        asset,
+    )
+    .await?;
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download ffmpeg: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to download ffmpeg: HTTP {}",
+            response.status()
+        ));
+    }
+
+    let total_size = response.content_length().unwrap_or(0);
+    let mut downloaded: u64 = 0;
+    let mut hasher = Sha256::new();
+
+    let archive_name = if url.ends_with(".tar.xz") { "ffmpeg.tar.xz" } else { "ffmpeg.zip" };
+    let archive_path = bin_dir.join(archive_name);
+    let mut file = fs::File::create(&archive_path)
+        .await
+        .map_err(|e| format!("Failed to create temp file: {}", e))?;
+
+    let mut stream = response.bytes_stream();
+    use futures::StreamExt;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Download error: {}", e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        hasher.update(&chunk);
+
+        downloaded += chunk.len() as u64;
+        progress_callback(downloaded, total_size);
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to flush file: {}", e))?;
+    drop(file);
+
+    // Nothing from the archive is unpacked or run unless it matches the release checksum
+    if let Err(e) = verify_sha256(hasher, &expected_hash, "ffmpeg") {
+        let _ = fs::remove_file(&archive_path).await;
+        return Err(e);
+    }
+
+    let extract_dir = bin_dir.join("ffmpeg-extract");
+    let result = install_from_archive(&archive_path, &extract_dir, &bin_dir).await;
+
+    let _ = fs::remove_file(&archive_path).await;
+    let _ = fs::remove_dir_all(&extract_dir).await;
+
+    result
+}
+
+/// Unpack an ffmpeg archive and copy ffmpeg/ffprobe into `bin_dir`
+async fn install_from_archive(archive: &Path, extract_dir: &Path, bin_dir: &Path) -> Result<PathBuf, String> {
+    let _ = fs::remove_dir_all(extract_dir).await;
+    fs::create_dir_all(extract_dir)
+        .await
+        .map_err(|e| format!("Failed to create extract directory: {}", e))?;
+
+    // The system tar handles .tar.xz on Linux and .zip on Windows 10+ and macOS (bsdtar)
+    let status = tokio::process::Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(extract_dir)
+        .status()
+        .await
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+    if !status.success() {
+        return Err("Failed to extract ffmpeg archive".to_string());
+    }
+
+    let ffmpeg = find_file(extract_dir, FFMPEG_BINARY_NAME)
+        .ok_or("ffmpeg binary not found in archive")?;
+    let target = bin_dir.join(FFMPEG_BINARY_NAME);
+    install_binary(&ffmpeg, &target).await?;
+
+    if let Some(ffprobe) = find_file(extract_dir, FFPROBE_BINARY_NAME) {
+        install_binary(&ffprobe, &bin_dir.join(FFPROBE_BINARY_NAME)).await?;
+    }
+
+    Ok(target)
+}
+
+async fn install_binary(source: &Path, target: &Path) -> Result<(), String> {
+    fs::copy(source, target)
+        .await
+        .map_err(|e| format!("Failed to install {}: {}", target.display(), e))?;
+
+    // Make executable on Unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(target)
+            .await
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+            .permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(target, perms)
+            .await
+            .map_err(|e| format!("Failed to set executable permission: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Recursively search `dir` for a file called `name`
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut subdirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if path.file_name().is_some_and(|n| n == name) {
+            return Some(path);
+        }
+    }
+    subdirs.into_iter().find_map(|d| find_file(&d, name))
+}
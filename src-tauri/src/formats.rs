@@ -0,0 +1,190 @@
+use crate::video::VideoFormat;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatMode {
+    /// Best video stream merged with the best audio stream
+    #[default]
+    VideoAudio,
+    /// A single format that already contains video and audio
+    Combined,
+    VideoOnly,
+    AudioOnly,
+}
+
+/// Structured format choice, turned into a yt-dlp `-f` expression plus `-S` sort keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatPreference {
+    pub mode: FormatMode,
+    pub max_height: Option<u32>,
+    /// Video codecs in order of preference, e.g. ["av1", "vp9"]
+    pub video_codecs: Vec<String>,
+    /// Audio codecs in order of preference, e.g. ["opus", "aac"]
+    pub audio_codecs: Vec<String>,
+    /// Preferred container; merged downloads are remuxed into it
+    pub container: Option<String>,
+    /// Fall back to the best available format when nothing matches
+    pub fallback: bool,
+}
+
+impl Default for FormatPreference {
+    fn default() -> Self {
+        Self {
+            mode: FormatMode::VideoAudio,
+            max_height: None,
+            video_codecs: Vec::new(),
+            audio_codecs: Vec::new(),
+            container: None,
+            fallback: true,
+        }
+    }
+}
+
+/// The yt-dlp arguments a preference resolves to
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct FormatSelector {
+    /// Value for `-f`
+    pub format: String,
+    /// Values for `-S`
+    pub sort: Vec<String>,
+    /// Value for `--merge-output-format`
+    pub merge_output_format: Option<String>,
+}
+
+/// A selector together with the formats yt-dlp resolved it to
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatPreview {
+    pub selector: FormatSelector,
+    pub formats: Vec<VideoFormat>,
+}
+
+const CONTAINERS: &[&str] = &["mp4", "webm", "mkv", "mov", "flv", "m4a", "mp3", "ogg", "opus"];
+
+/// Containers yt-dlp can merge separate video and audio streams into
+const MERGE_CONTAINERS: &[&str] = &["mp4", "webm", "mkv", "mov", "flv"];
+
+/// Regex matching yt-dlp codec names for a user-facing codec name
+fn codec_pattern(codec: &str) -> Option<&'static str> {
+    match codec.trim().to_lowercase().as_str() {
+        "av1" | "av01" => Some("^av0?1"),
+        "vp9" | "vp09" => Some("^vp0?9"),
+        "h264" | "avc" | "avc1" => Some("^(avc1|h264)"),
+        "h265" | "hevc" | "hvc1" => Some("^(hvc1|hev1|h265|hevc)"),
+        "opus" => Some("^opus"),
+        "aac" | "mp4a" => Some("^(mp4a|aac)"),
+        "vorbis" => Some("^vorbis"),
+        "mp3" => Some("^mp3"),
+        _ => None,
+    }
+}
+
+/// Codec filters in preference order; an empty filter means "any codec"
+fn codec_filters(field: &str, codecs: &[String]) -> Result<Vec<String>, String> {
+    let mut filters = codecs
+        .iter()
+        .map(|c| {
+            codec_pattern(c)
+                .map(|p| format!("[{}~='{}']", field, p))
+                .ok_or_else(|| format!("Unknown codec: {}", c))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if filters.is_empty() {
+        filters.push(String::new());
+    }
+    Ok(filters)
+}
+
+impl FormatPreference {
+    pub fn to_selector(&self) -> Result<FormatSelector, String> {
+        let container = self
+            .container
+            .as_ref()
+            .map(|c| c.trim().trim_start_matches('.').to_lowercase())
+            .filter(|c| !c.is_empty());
+        if let Some(c) = &container {
+            if !CONTAINERS.contains(&c.as_str()) {
+                return Err(format!("Unsupported container: {}", c));
+            }
+        }
+
+        let height = self
+            .max_height
+            .map(|h| format!("[height<={}]", h))
+            .unwrap_or_default();
+        let video = codec_filters("vcodec", &self.video_codecs)?;
+        let audio = codec_filters("acodec", &self.audio_codecs)?;
+
+        let mut alternatives = Vec::new();
+        match self.mode {
+            FormatMode::VideoAudio => {
+                for v in &video {
+                    for a in &audio {
+                        alternatives.push(format!("bv*{}{}+ba{}", height, v, a));
+                    }
+                }
+                if self.fallback {
+                    alternatives.push(format!("bv*{}+ba", height));
+                    alternatives.push(format!("b{}", height));
+                }
+            }
+            FormatMode::Combined => {
+                for v in &video {
+                    for a in &audio {
+                        alternatives.push(format!("b{}{}{}", height, v, a));
+                    }
+                }
+                if self.fallback {
+                    alternatives.push(format!("b{}", height));
+                }
+            }
+            FormatMode::VideoOnly => {
+                for v in &video {
+                    alternatives.push(format!("bv{}{}", height, v));
+                }
+                if self.fallback {
+                    alternatives.push(format!("bv{}", height));
+                }
+            }
+            FormatMode::AudioOnly => {
+                for a in &audio {
+                    alternatives.push(format!("ba{}", a));
+                }
+                if self.fallback {
+                    alternatives.push("ba".to_string());
+                }
+            }
+        }
+        if self.fallback {
+            // Last resort when even the height limit can't be met
+            if self.mode != FormatMode::AudioOnly {
+                alternatives.push("bv*+ba".to_string());
+            }
+            alternatives.push("b".to_string());
+        }
+
+        // Keep the first occurrence of each alternative
+        let mut unique: Vec<String> = Vec::new();
+        for alternative in alternatives {
+            if !unique.contains(&alternative) {
+                unique.push(alternative);
+            }
+        }
+
+        let mut sort = Vec::new();
+        if let Some(h) = self.max_height {
+            sort.push(format!("res:{}", h));
+        }
+        if let Some(c) = &container {
+            sort.push(format!("ext:{}", c));
+        }
+
+        Ok(FormatSelector {
+            format: unique.join("/"),
+            sort,
+            merge_output_format: container
+                .filter(|c| self.mode == FormatMode::VideoAudio && MERGE_CONTAINERS.contains(&c.as_str())),
+        })
+    }
+}
@@ -1,7 +1,20 @@
+mod cli;
 mod commands;
+mod cookies;
+mod database;
 mod downloader;
+mod export;
+mod ffmpeg;
+mod formats;
+mod logs;
+mod migrations;
+mod native;
 mod persistence;
+mod query;
+mod rpc;
+mod sites;
 mod state;
+mod thumbnails;
 mod utils;
 mod video;
 mod ytdlp;
@@ -9,13 +22,25 @@ mod ytdlp;
 use persistence::{DownloadHistory, DownloadStatus};
 use state::{AppState, Settings};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Manager,
 };
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tokio::sync::RwLock;
 
+/// Entry point for the `wdm-cli` binary
+pub fn run_cli() -> std::process::ExitCode {
+    cli::run()
+}
+
+/// Entry point for the `wdm-native-host` binary browsers start
+pub fn run_native_host() -> std::process::ExitCode {
+    native::run()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -25,25 +50,47 @@ pub fn run() {
             // Load history and settings
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                let mut history = DownloadHistory::load().await;
+                let mut history = match DownloadHistory::load().await {
+                    Ok(history) => history,
+                    Err(e) => {
+                        eprintln!("Failed to load download history: {}", e);
+                        handle
+                            .dialog()
+                            .message(format!(
+                                "Download history could not be opened, so downloads started now won't be remembered.\n\n{}",
+                                e
+                            ))
+                            .title("WDM")
+                            .kind(MessageDialogKind::Error)
+                            .show(|_| {});
+                        DownloadHistory::default()
+                    }
+                };
                 let settings = Settings::load().await;
 
                 // Mark any "Downloading" status as "Paused" since app was closed
-                let mut needs_save = false;
-                for record in history.downloads.values_mut() {
-                    if record.status == DownloadStatus::Downloading {
-                        record.status = DownloadStatus::Paused;
-                        record.updated_at = chrono::Utc::now().timestamp();
-                        needs_save = true;
-                    }
+                let interrupted: Vec<String> = history
+                    .downloads
+                    .values()
+                    .filter(|r| r.status == DownloadStatus::Downloading)
+                    .map(|r| r.id.clone())
+                    .collect();
+                for id in &interrupted {
+                    history.update_download(id, |r| {
+                        r.status = DownloadStatus::Paused;
+                    });
                 }
-                if needs_save {
-                    let _ = history.save().await;
+
+                if let Err(e) = sites::set_user_patterns(&settings.video_detection.patterns) {
+                    eprintln!("Ignoring video URL patterns from settings: {}", e);
                 }
 
                 let state = handle.state::<AppState>();
                 *state.history.write().await = history;
                 *state.settings.write().await = settings;
+
+                commands::run_rpc_server(handle.clone()).await;
+                commands::run_ytdlp_auto_update(handle.clone()).await;
             });
 
             // Create system tray
@@ -102,7 +149,8 @@ pub fn run() {
             downloads: RwLock::new(HashMap::new()),
             video_downloads: RwLock::new(HashMap::new()),
             settings: RwLock::new(Settings::default()),
-            history: RwLock::new(DownloadHistory::default()),
+            history: Arc::new(RwLock::new(DownloadHistory::default())),
+            rpc_server: RwLock::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             commands::fetch_url_info,
@@ -120,19 +168,64 @@ pub fn run() {
             commands::get_speed_limit,
             commands::set_speed_limit,
             commands::get_download_history,
+            commands::query_download_history,
             commands::clear_download_history,
             commands::remove_from_history,
+            commands::export_history,
+            commands::import_history,
+            commands::start_queued_download,
             commands::open_file,
+            commands::open_download_log,
             commands::show_in_folder,
             // Video commands
             commands::check_video_url,
             commands::check_ytdlp_installed,
             commands::install_ytdlp,
             commands::get_ytdlp_ver,
+            commands::update_ytdlp,
+            commands::check_ytdlp_update,
+            commands::get_ytdlp_update_settings,
+            commands::set_ytdlp_update_settings,
+            commands::get_ytdlp_command,
+            commands::set_ytdlp_command,
+            commands::get_ytdlp_extra_args,
+            commands::set_ytdlp_extra_args,
+            commands::get_output_template,
+            commands::set_output_template,
+            commands::get_video_detection,
+            commands::set_video_detection,
+            commands::get_embed_options,
+            commands::set_embed_options,
+            commands::get_rpc_settings,
+            commands::set_rpc_settings,
+            commands::get_site_cookies,
+            commands::set_site_cookies,
+            commands::check_ffmpeg_installed,
+            commands::get_ffmpeg_info,
+            commands::install_ffmpeg,
+            commands::set_ffmpeg_path,
             commands::get_video_info,
+            commands::preview_format_selection,
             commands::start_video_download,
-            commands::cancel_video_download
+            commands::check_playlist_url,
+            commands::get_playlist_info,
+            commands::start_playlist_download,
+            commands::cancel_video_download,
+            commands::pause_video_download,
+            commands::resume_video_download,
+            commands::stop_video_recording
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Write out any queued history changes before the process exits
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<AppState>();
+                tauri::async_runtime::block_on(async {
+                    if let Err(e) = state.history.read().await.flush().await {
+                        eprintln!("Failed to flush download history: {}", e);
+                    }
+                });
+            }
+        });
 }
\ No newline at end of file
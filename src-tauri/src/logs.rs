@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+/// Size at which a download log is rotated
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Rotated logs kept next to the current one (`<id>.log.1`, `<id>.log.2`, ...)
+const MAX_ROTATED_LOGS: u32 = 2;
+
+/// Get the directory where per-download logs are stored
+pub fn get_logs_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wdm")
+        .join("logs")
+}
+
+/// Get the path of a download's current log file
+pub fn get_log_path(id: &str) -> PathBuf {
+    get_logs_dir().join(format!("{}.log", id))
+}
+
+fn rotated_path(id: &str, n: u32) -> PathBuf {
+    get_logs_dir().join(format!("{}.log.{}", id, n))
+}
+
+/// Delete a download's log and its rotated copies
+pub async fn remove_logs(id: &str) {
+    let _ = fs::remove_file(get_log_path(id)).await;
+    for n in 1..=MAX_ROTATED_LOGS {
+        let _ = fs::remove_file(rotated_path(id, n)).await;
+    }
+}
+
+/// Append-only log of a download's yt-dlp output
+pub struct DownloadLog {
+    id: String,
+    file: Option<File>,
+    size: u64,
+}
+
+impl DownloadLog {
+    pub async fn open(id: &str) -> Self {
+        let mut log = Self {
+            id: id.to_string(),
+            file: None,
+            size: 0,
+        };
+        log.reopen().await;
+        log
+    }
+
+    async fn reopen(&mut self) {
+        let path = get_log_path(&self.id);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent).await;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&path).await.ok();
+        self.size = fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+    }
+
+    /// Shift `<id>.log` to `<id>.log.1`, `.1` to `.2` and so on, dropping the oldest
+    async fn rotate(&mut self) {
+        self.file = None;
+        for n in (1..MAX_ROTATED_LOGS).rev() {
+            let _ = fs::rename(rotated_path(&self.id, n), rotated_path(&self.id, n + 1)).await;
+        }
+        let _ = fs::rename(get_log_path(&self.id), rotated_path(&self.id, 1)).await;
+        self.reopen().await;
+    }
+
+    /// Write one line tagged with its stream ("stdout" or "stderr").
+    /// Logging is best effort and never fails the download.
+    pub async fn write_line(&mut self, stream: &str, line: &str) {
+        if self.size >= MAX_LOG_SIZE {
+            self.rotate().await;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+
+        let entry = format!(
+            "{} [{}] {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            stream,
+            line
+        );
+        if file.write_all(entry.as_bytes()).await.is_ok() {
+            self.size += entry.len() as u64;
+        }
+    }
+
+    pub async fn flush(&mut self) {
+        if let Some(file) = self.file.as_mut() {
+            let _ = file.flush().await;
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Current version of `settings.json`
+pub const SETTINGS_VERSION: u64 = 10;
+
+/// Current version of JSON history documents (the legacy `downloads.json`)
+pub const HISTORY_VERSION: u64 = 6;
+
+const VERSION_KEY: &str = "schema_version";
+
+/// A step that upgrades a document from version `n` to `n + 1`
+type Migration = fn(&mut Value);
+
+/// Settings migrations, indexed by the version they upgrade from
+const SETTINGS_MIGRATIONS: &[Migration] = &[
+    settings_v0_to_v1,
+    settings_v1_to_v2,
+    settings_v2_to_v3,
+    settings_v3_to_v4,
+    settings_v4_to_v5,
+    settings_v5_to_v6,
+    settings_v6_to_v7,
+    settings_v7_to_v8,
+    settings_v8_to_v9,
+    settings_v9_to_v10,
+];
+
+/// History migrations, indexed by the version they upgrade from
+const HISTORY_MIGRATIONS: &[Migration] = &[
+    history_v0_to_v1,
+    history_v1_to_v2,
+    history_v2_to_v3,
+    history_v3_to_v4,
+    history_v4_to_v5,
+    history_v5_to_v6,
+];
+
+/// Files written before versioning was introduced count as version 0
+pub fn document_version(value: &Value) -> u64 {
+    value.get(VERSION_KEY).and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+pub fn stamp_version(value: &mut Value, version: u64) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert(VERSION_KEY.to_string(), Value::from(version));
+    }
+}
+
+pub fn migrate_settings(value: &mut Value) {
+    migrate(value, SETTINGS_MIGRATIONS, SETTINGS_VERSION);
+}
+
+pub fn migrate_history(value: &mut Value) {
+    migrate(value, HISTORY_MIGRATIONS, HISTORY_VERSION);
+}
+
+fn migrate(value: &mut Value, migrations: &[Migration], target: u64) {
+    let mut version = document_version(value);
+    while version < target {
+        migrations[version as usize](value);
+        version += 1;
+    }
+    stamp_version(value, version);
+}
+
+/// Keep a copy of a file written by a newer version of WDM before we touch it
+pub async fn backup_newer_file(path: &Path, version: u64, supported: u64) -> Option<PathBuf> {
+    if version <= supported {
+        return None;
+    }
+
+    let backup = backup_path(path, version);
+    eprintln!(
+        "{} has schema version {} but this build only understands {}; keeping a copy at {}",
+        path.display(),
+        version,
+        supported,
+        backup.display()
+    );
+    match tokio::fs::copy(path, &backup).await {
+        Ok(_) => Some(backup),
+        Err(e) => {
+            eprintln!("Failed to back up {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn backup_path(path: &Path, version: u64) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+// v0 settings relied on serde defaults for `speed_limit`
+fn settings_v0_to_v1(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("speed_limit").or_insert(Value::from(0));
+    }
+}
+
+// v2 adds a custom ffmpeg location
+fn settings_v1_to_v2(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("ffmpeg_path").or_insert(Value::Null);
+    }
+}
+
+// v3 adds yt-dlp update preferences; missing fields take their defaults
+fn settings_v2_to_v3(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("ytdlp_update").or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+// v4 adds a custom yt-dlp binary and extra arguments
+fn settings_v3_to_v4(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("ytdlp_path").or_insert(Value::Null);
+        obj.entry("ytdlp_extra_args").or_insert_with(|| Value::Array(Vec::new()));
+    }
+}
+
+// v5 adds per-site cookies
+fn settings_v4_to_v5(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("site_cookies").or_insert_with(|| Value::Array(Vec::new()));
+    }
+}
+
+// v6 adds the video output template
+fn settings_v5_to_v6(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("output_template").or_insert_with(|| Value::from("%(title)s.%(ext)s"));
+    }
+}
+
+// v7 adds user video URL patterns and the yt-dlp probe toggle
+fn settings_v6_to_v7(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("video_detection").or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+// v8 adds default embed options for video downloads
+fn settings_v7_to_v8(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("embed").or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+// v9 adds the local JSON-RPC server, off by default
+fn settings_v8_to_v9(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("rpc").or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+// v10 replaces the custom yt-dlp binary path with a command and its arguments
+fn settings_v9_to_v10(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        let command = match obj.remove("ytdlp_path") {
+            Some(Value::String(path)) if !path.trim().is_empty() => vec![Value::String(path)],
+            _ => Vec::new(),
+        };
+        obj.entry("ytdlp_command").or_insert(Value::Array(command));
+    }
+}
+
+/// Insert `defaults` into every history record that lacks them
+fn add_record_defaults(value: &mut Value, defaults: &[(&str, Value)]) {
+    let Some(downloads) = value.get_mut("downloads").and_then(|d| d.as_object_mut()) else {
+        return;
+    };
+    for record in downloads.values_mut() {
+        if let Some(obj) = record.as_object_mut() {
+            for (key, default) in defaults {
+                obj.entry(*key).or_insert_with(|| default.clone());
+            }
+        }
+    }
+}
+
+// v0 records predate video support and have no `is_video`/`thumbnail`
+fn history_v0_to_v1(value: &mut Value) {
+    add_record_defaults(value, &[("is_video", Value::Bool(false)), ("thumbnail", Value::Null)]);
+}
+
+// v2 adds playlist grouping
+fn history_v1_to_v2(value: &mut Value) {
+    add_record_defaults(value, &[("parent_id", Value::Null), ("is_playlist", Value::Bool(false))]);
+}
+
+// v3 keeps yt-dlp options on video records so they can be resumed
+fn history_v2_to_v3(value: &mut Value) {
+    add_record_defaults(value, &[("video_options", Value::Null)]);
+}
+
+// v4 tracks subtitle files downloaded with a video
+fn history_v3_to_v4(value: &mut Value) {
+    add_record_defaults(value, &[("subtitle_files", Value::Array(Vec::new()))]);
+}
+
+// v5 keeps the metadata yt-dlp reports for finished videos
+fn history_v4_to_v5(value: &mut Value) {
+    add_record_defaults(value, &[("video_metadata", Value::Null)]);
+}
+
+// v6 keeps request headers passed in by the browser extension
+fn history_v5_to_v6(value: &mut Value) {
+    add_record_defaults(value, &[("headers", Value::Array(Vec::new()))]);
+}
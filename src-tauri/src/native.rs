@@ -0,0 +1,296 @@
+use crate::state::Settings;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Name the browser extension connects to; it must match the manifest file name
+const HOST_NAME: &str = "com.wdm.native_host";
+
+/// Browsers cap messages from the host at 1 MB; requests are held to the same size
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Headers the download engine sets itself, or that make no sense for a new request
+const SKIPPED_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "range",
+    "accept-encoding",
+    "cookie",
+    "referer",
+];
+
+const USAGE: &str = "\
+Usage: wdm-native-host [--install | --uninstall] [options]
+
+Browsers start this program themselves to hand downloads to WDM.
+
+  --install                 Register the host with Chrome, Chromium, Brave, Edge and Firefox
+  --uninstall               Remove the registrations
+  --chrome-extension <id>   Chromium-based extension allowed to connect (repeatable)
+  --firefox-extension <id>  Firefox extension allowed to connect (repeatable)
+";
+
+/// What the extension sends, one JSON message at a time
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum HostMessage {
+    Ping,
+    Download(InterceptedDownload),
+}
+
+/// A download the browser caught, with what it needs to fetch the file the same way
+#[derive(Deserialize)]
+struct InterceptedDownload {
+    url: String,
+    #[serde(default)]
+    referrer: Option<String>,
+    #[serde(default)]
+    cookies: Option<String>, // "name=value; name2=value2"
+    #[serde(default)]
+    filename: Option<String>, // may be a full path suggested by the browser
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+pub fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("--install") => install(&args[1..]),
+        Some("--uninstall") => uninstall(),
+        Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        // Browsers pass the caller's origin or manifest path; nothing to act on
+        _ => serve(),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("wdm-native-host: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Answer messages on stdin until the browser closes the port
+fn serve() -> Result<(), String> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+
+    while let Some(message) = read_message(&mut stdin)? {
+        let reply = match serde_json::from_slice::<HostMessage>(&message) {
+            Ok(HostMessage::Ping) => json!({ "ok": true, "version": env!("CARGO_PKG_VERSION") }),
+            Ok(HostMessage::Download(download)) => match runtime.block_on(send_to_app(download)) {
+                Ok(gid) => json!({ "ok": true, "gid": gid }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            },
+            Err(e) => json!({ "ok": false, "error": format!("Invalid message: {}", e) }),
+        };
+        write_message(&mut stdout, &reply)?;
+    }
+    Ok(())
+}
+
+/// Read one length-prefixed message; `None` once stdin is closed
+fn read_message(input: &mut impl Read) -> Result<Option<Vec<u8>>, String> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(format!("Failed to read message: {}", e)),
+    }
+
+    // The length is in native byte order
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(format!("Message of {} bytes is too large", length));
+    }
+    let mut message = vec![0u8; length];
+    input
+        .read_exact(&mut message)
+        .map_err(|e| format!("Failed to read message: {}", e))?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<(), String> {
+    let bytes = serde_json::to_vec(message).map_err(|e| format!("Failed to serialize reply: {}", e))?;
+    output
+        .write_all(&(bytes.len() as u32).to_ne_bytes())
+        .and_then(|_| output.write_all(&bytes))
+        .and_then(|_| output.flush())
+        .map_err(|e| format!("Failed to write reply: {}", e))
+}
+
+/// Hand a download to the running app through its JSON-RPC server
+async fn send_to_app(download: InterceptedDownload) -> Result<String, String> {
+    let rpc = Settings::load().await.rpc;
+    if !rpc.enabled {
+        return Err("Turn on the JSON-RPC server in WDM's settings to catch browser downloads".to_string());
+    }
+
+    let mut options = serde_json::Map::new();
+    if let Some(name) = download.filename.as_deref().and_then(file_name) {
+        options.insert("out".to_string(), Value::String(name));
+    }
+    if let Some(referrer) = download.referrer.filter(|r| !r.is_empty()) {
+        options.insert("referer".to_string(), Value::String(referrer));
+    }
+    let mut headers: Vec<Value> = download
+        .headers
+        .iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.to_lowercase().as_str()))
+        .map(|(name, value)| Value::String(format!("{}: {}", name, value)))
+        .collect();
+    if let Some(cookies) = download.cookies.filter(|c| !c.is_empty()) {
+        headers.push(Value::String(format!("Cookie: {}", cookies)));
+    }
+    options.insert("header".to_string(), Value::Array(headers));
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": HOST_NAME,
+        "method": "aria2.addUri",
+        "params": [format!("token:{}", rpc.secret), [download.url], options],
+    });
+
+    let client = reqwest::Client::builder()
+        .timeout(RPC_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))?;
+    let response: Value = client
+        .post(format!("http://127.0.0.1:{}/jsonrpc", rpc.port))
+        .json(&request)
+        .send()
+        .await
+        .map_err(|_| "WDM is not running".to_string())?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid reply from WDM: {}", e))?;
+
+    if let Some(gid) = response.get("result").and_then(|r| r.as_str()) {
+        return Ok(gid.to_string());
+    }
+    Err(response
+        .pointer("/error/message")
+        .and_then(|m| m.as_str())
+        .unwrap_or("WDM did not accept the download")
+        .to_string())
+}
+
+/// Last component of a browser-suggested path, whichever separator it uses
+fn file_name(path: &str) -> Option<String> {
+    path.rsplit(['/', '\\'])
+        .next()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+/// Where each browser looks for host manifests, and whether it is Firefox
+fn manifest_dirs() -> Vec<(PathBuf, bool)> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    let (Some(config), Some(home)) = (dirs::config_dir(), dirs::home_dir()) else {
+        return Vec::new();
+    };
+
+    let chromium = ["google-chrome", "chromium", "BraveSoftware/Brave-Browser", "microsoft-edge"];
+    let mut dirs: Vec<(PathBuf, bool)> = chromium
+        .iter()
+        .map(|browser| (config.join(browser), false))
+        .collect();
+    dirs.push((home.join(".mozilla"), true));
+
+    // Only browsers that have been run, so uninstalled ones get no stray folders
+    dirs.into_iter()
+        .filter(|(profile, _)| profile.is_dir())
+        .map(|(profile, firefox)| {
+            let hosts = if firefox { "native-messaging-hosts" } else { "NativeMessagingHosts" };
+            (profile.join(hosts), firefox)
+        })
+        .collect()
+}
+
+/// Write the host manifest for every installed browser
+fn install(args: &[String]) -> Result<(), String> {
+    let mut chrome_ids = Vec::new();
+    let mut firefox_ids = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--chrome-extension" => chrome_ids.push(format!("chrome-extension://{}/", value)),
+            "--firefox-extension" => firefox_ids.push(value.clone()),
+            other => return Err(format!("Unknown option: {}\n\n{}", other, USAGE)),
+        }
+    }
+    if chrome_ids.is_empty() && firefox_ids.is_empty() {
+        return Err(format!("Pass at least one extension id\n\n{}", USAGE));
+    }
+
+    if !cfg!(target_os = "linux") {
+        return Err("Installing the host manifests is only supported on Linux".to_string());
+    }
+    let path = std::env::current_exe()
+        .and_then(|p| p.canonicalize())
+        .map_err(|e| format!("Failed to locate wdm-native-host: {}", e))?;
+
+    let mut installed = 0;
+    for (dir, firefox) in manifest_dirs() {
+        let (key, ids) = if firefox {
+            ("allowed_extensions", &firefox_ids)
+        } else {
+            ("allowed_origins", &chrome_ids)
+        };
+        if ids.is_empty() {
+            continue;
+        }
+
+        let manifest = json!({
+            "name": HOST_NAME,
+            "description": "WDM - Web Download Manager",
+            "path": path,
+            "type": "stdio",
+            key: ids,
+        });
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let file = dir.join(format!("{}.json", HOST_NAME));
+        let content = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+        std::fs::write(&file, content)
+            .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+        println!("Installed {}", file.display());
+        installed += 1;
+    }
+
+    if installed == 0 {
+        return Err("No supported browser found".to_string());
+    }
+    Ok(())
+}
+
+fn uninstall() -> Result<(), String> {
+    for (dir, _) in manifest_dirs() {
+        let file = dir.join(format!("{}.json", HOST_NAME));
+        if file.exists() {
+            std::fs::remove_file(&file)
+                .map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+            println!("Removed {}", file.display());
+        }
+    }
+    Ok(())
+}
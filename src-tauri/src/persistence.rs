@@ -1,7 +1,13 @@
+use crate::database::{HistoryBatch, HistoryDb};
+use crate::migrations::{backup_newer_file, document_version, migrate_history, HISTORY_VERSION};
+use crate::video::{VideoMetadata, VideoOptions};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
+use tokio::sync::{mpsc, oneshot};
 
 /// Persistent download record - saved to disk
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -19,6 +25,24 @@ pub struct DownloadRecord {
     pub is_video: bool,
     #[serde(default)]
     pub thumbnail: Option<String>,
+    /// Playlist record this download belongs to, or the video a chapter file was split from
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Group record for a playlist; its entries point back to it via `parent_id`
+    #[serde(default)]
+    pub is_playlist: bool,
+    /// yt-dlp options used for a video download, needed to resume it
+    #[serde(default)]
+    pub video_options: Option<VideoOptions>,
+    /// Subtitle files written next to a downloaded video
+    #[serde(default)]
+    pub subtitle_files: Vec<String>,
+    /// What yt-dlp reported about the finished video
+    #[serde(default)]
+    pub video_metadata: Option<VideoMetadata>,
+    /// Extra request headers ("Name: value") from the browser, cleared once the download ends
+    #[serde(default)]
+    pub headers: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -33,6 +57,31 @@ pub enum DownloadStatus {
     Cancelled,
 }
 
+impl DownloadStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DownloadStatus::Pending => "Pending",
+            DownloadStatus::Downloading => "Downloading",
+            DownloadStatus::Paused => "Paused",
+            DownloadStatus::Completed => "Completed",
+            DownloadStatus::Failed => "Failed",
+            DownloadStatus::Cancelled => "Cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Pending" => Some(DownloadStatus::Pending),
+            "Downloading" => Some(DownloadStatus::Downloading),
+            "Paused" => Some(DownloadStatus::Paused),
+            "Completed" => Some(DownloadStatus::Completed),
+            "Failed" => Some(DownloadStatus::Failed),
+            "Cancelled" => Some(DownloadStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ChunkRecord {
     pub id: u64,
@@ -41,58 +90,229 @@ pub struct ChunkRecord {
     pub downloaded: u64,
 }
 
-#[derive(Clone, Serialize, Deserialize, Default)]
+/// How often the history writer flushes queued changes to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Download history, cached in memory. Changes are queued to the history
+/// writer, which persists them to SQLite in the background.
+#[derive(Default)]
 pub struct DownloadHistory {
     pub downloads: HashMap<String, DownloadRecord>,
+    writer: Option<mpsc::UnboundedSender<HistoryEvent>>,
+    // Store behind the cache, used for queries
+    db: Option<Arc<HistoryDb>>,
+}
+
+/// A change sent to the history writer
+pub enum HistoryEvent {
+    Upsert(Box<DownloadRecord>),
+    Chunk {
+        id: String,
+        chunk: ChunkRecord,
+        updated_at: i64,
+    },
+    Remove(String),
+    Flush(oneshot::Sender<Result<(), String>>),
+}
+
+/// Changes received since the last flush, merged per record
+#[derive(Default)]
+struct PendingWrites {
+    records: HashMap<String, DownloadRecord>,
+    chunks: HashMap<(String, u64), (ChunkRecord, i64)>,
+    removed: HashSet<String>,
+}
+
+impl PendingWrites {
+    fn merge(&mut self, event: HistoryEvent) {
+        match event {
+            HistoryEvent::Upsert(record) => {
+                // The full record already carries its chunks
+                self.chunks.retain(|(id, _), _| id != &record.id);
+                self.removed.remove(&record.id);
+                self.records.insert(record.id.clone(), *record);
+            }
+            HistoryEvent::Chunk { id, chunk, updated_at } => {
+                if let Some(record) = self.records.get_mut(&id) {
+                    if let Some(existing) = record.chunks.iter_mut().find(|c| c.id == chunk.id) {
+                        *existing = chunk;
+                    }
+                    record.updated_at = updated_at;
+                } else if !self.removed.contains(&id) {
+                    self.chunks.insert((id, chunk.id), (chunk, updated_at));
+                }
+            }
+            HistoryEvent::Remove(id) => {
+                self.records.remove(&id);
+                self.chunks.retain(|(chunk_id, _), _| chunk_id != &id);
+                self.removed.insert(id);
+            }
+            HistoryEvent::Flush(_) => {}
+        }
+    }
+
+    fn take_batch(&mut self) -> HistoryBatch {
+        let pending = std::mem::take(self);
+        HistoryBatch {
+            records: pending.records.into_values().collect(),
+            chunks: pending
+                .chunks
+                .into_iter()
+                .map(|((id, _), (chunk, updated_at))| (id, chunk, updated_at))
+                .collect(),
+            removed: pending.removed.into_iter().collect(),
+        }
+    }
+}
+
+async fn flush_pending(db: &Arc<HistoryDb>, pending: &mut PendingWrites) -> Result<(), String> {
+    let batch = pending.take_batch();
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let db = Arc::clone(db);
+    tokio::task::spawn_blocking(move || db.apply(&batch))
+        .await
+        .map_err(|e| format!("History task failed: {}", e))?
+}
+
+/// Background task that owns all writes to the history database
+async fn run_history_writer(db: Arc<HistoryDb>, mut rx: mpsc::UnboundedReceiver<HistoryEvent>) {
+    let mut pending = PendingWrites::default();
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(HistoryEvent::Flush(reply)) => {
+                    let _ = reply.send(flush_pending(&db, &mut pending).await);
+                }
+                Some(event) => pending.merge(event),
+                None => {
+                    if let Err(e) = flush_pending(&db, &mut pending).await {
+                        eprintln!("Failed to write download history: {}", e);
+                    }
+                    break;
+                }
+            },
+            _ = ticker.tick() => {
+                if let Err(e) = flush_pending(&db, &mut pending).await {
+                    eprintln!("Failed to write download history: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Layout of the legacy `downloads.json` file
+#[derive(Deserialize, Default)]
+struct LegacyHistory {
+    downloads: HashMap<String, DownloadRecord>,
 }
 
 impl DownloadHistory {
     fn get_data_path() -> PathBuf {
-        let data_dir = dirs::data_dir()
+        dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
-            .join("wdm");
-        data_dir
+            .join("wdm")
     }
 
     fn get_history_file() -> PathBuf {
         Self::get_data_path().join("downloads.json")
     }
 
-    pub async fn load() -> Self {
-        let path = Self::get_history_file();
+    fn get_database_file() -> PathBuf {
+        Self::get_data_path().join("downloads.db")
+    }
 
-        if !path.exists() {
-            return Self::default();
-        }
+    /// Open the history database. Without it nothing would be saved, so
+    /// callers have to handle the error rather than carry on silently.
+    pub async fn load() -> Result<Self, String> {
+        fs::create_dir_all(Self::get_data_path())
+            .await
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
 
-        match fs::read_to_string(&path).await {
-            Ok(content) => {
-                serde_json::from_str(&content).unwrap_or_default()
+        let db = tokio::task::spawn_blocking(|| HistoryDb::open(&Self::get_database_file()))
+            .await
+            .map_err(|e| format!("History task failed: {}", e))??;
+        let db = Arc::new(db);
+
+        // A legacy file that can't be imported stays in place for another try
+        let legacy_path = Self::get_history_file();
+        if legacy_path.exists() {
+            if let Err(e) = Self::import_legacy(&db, &legacy_path).await {
+                eprintln!("Failed to import {}: {}", legacy_path.display(), e);
             }
-            Err(_) => Self::default(),
         }
-    }
 
-    pub async fn save(&self) -> Result<(), String> {
-        let data_dir = Self::get_data_path();
+        let db_clone = Arc::clone(&db);
+        let downloads = tokio::task::spawn_blocking(move || db_clone.load_all())
+            .await
+            .map_err(|e| format!("History task failed: {}", e))??;
 
-        // Create directory if it doesn't exist
-        fs::create_dir_all(&data_dir)
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_history_writer(Arc::clone(&db), rx));
+
+        Ok(Self {
+            downloads,
+            writer: Some(tx),
+            db: Some(db),
+        })
+    }
+
+    /// One-time import of the old JSON history file
+    async fn import_legacy(db: &Arc<HistoryDb>, legacy_path: &Path) -> Result<(), String> {
+        let content = fs::read_to_string(legacy_path)
             .await
-            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+            .map_err(|e| format!("Failed to read history file: {}", e))?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse history file: {}", e))?;
+        backup_newer_file(legacy_path, document_version(&value), HISTORY_VERSION).await;
+        migrate_history(&mut value);
+        let legacy: LegacyHistory = serde_json::from_value(value)
+            .map_err(|e| format!("Failed to parse history file: {}", e))?;
+        let batch = HistoryBatch {
+            records: legacy.downloads.into_values().collect(),
+            ..Default::default()
+        };
 
-        let path = Self::get_history_file();
-        let content = serde_json::to_string_pretty(self)
-            .map_err(|e| format!("Failed to serialize history: {}", e))?;
+        let db = Arc::clone(db);
+        tokio::task::spawn_blocking(move || db.apply(&batch))
+            .await
+            .map_err(|e| format!("History task failed: {}", e))??;
 
-        fs::write(&path, content)
+        fs::rename(legacy_path, legacy_path.with_extension("json.migrated"))
             .await
-            .map_err(|e| format!("Failed to write history file: {}", e))?;
+            .map_err(|e| format!("Failed to rename history file: {}", e))
+    }
+
+    /// Write all queued changes to disk and wait for them to land
+    /// The history database, if one was opened
+    pub fn database(&self) -> Option<Arc<HistoryDb>> {
+        self.db.clone()
+    }
 
-        Ok(())
+    pub async fn flush(&self) -> Result<(), String> {
+        let Some(writer) = &self.writer else {
+            return Ok(());
+        };
+        let (tx, rx) = oneshot::channel();
+        writer
+            .send(HistoryEvent::Flush(tx))
+            .map_err(|_| "History writer has stopped".to_string())?;
+        rx.await
+            .map_err(|_| "History writer has stopped".to_string())?
+    }
+
+    fn queue(&self, event: HistoryEvent) {
+        if let Some(writer) = &self.writer {
+            let _ = writer.send(event);
+        }
     }
 
     pub fn add_download(&mut self, record: DownloadRecord) {
+        self.queue(HistoryEvent::Upsert(Box::new(record.clone())));
         self.downloads.insert(record.id.clone(), record);
     }
 
@@ -100,15 +320,23 @@ impl DownloadHistory {
         if let Some(record) = self.downloads.get_mut(id) {
             updater(record);
             record.updated_at = chrono::Utc::now().timestamp();
+            let event = HistoryEvent::Upsert(Box::new(record.clone()));
+            self.queue(event);
         }
     }
 
     pub fn update_chunk_progress(&mut self, id: &str, chunk_id: u64, downloaded: u64) {
         if let Some(record) = self.downloads.get_mut(id) {
+            record.updated_at = chrono::Utc::now().timestamp();
             if let Some(chunk) = record.chunks.iter_mut().find(|c| c.id == chunk_id) {
                 chunk.downloaded = downloaded;
+                let event = HistoryEvent::Chunk {
+                    id: id.to_string(),
+                    chunk: chunk.clone(),
+                    updated_at: record.updated_at,
+                };
+                self.queue(event);
             }
-            record.updated_at = chrono::Utc::now().timestamp();
         }
     }
 
@@ -127,6 +355,8 @@ impl DownloadHistory {
                 });
             }
             record.updated_at = chrono::Utc::now().timestamp();
+            let event = HistoryEvent::Upsert(Box::new(record.clone()));
+            self.queue(event);
         }
     }
 
@@ -141,7 +371,9 @@ impl DownloadHistory {
     }
 
     pub fn remove_download(&mut self, id: &str) {
-        self.downloads.remove(id);
+        if self.downloads.remove(id).is_some() {
+            self.queue(HistoryEvent::Remove(id.to_string()));
+        }
     }
 }
 
@@ -198,6 +430,12 @@ impl DownloadRecord {
             chunks,
             is_video,
             thumbnail,
+            parent_id: None,
+            is_playlist: false,
+            video_options: None,
+            subtitle_files: Vec::new(),
+            video_metadata: None,
+            headers: Vec::new(),
             created_at: now,
             updated_at: now,
         }
@@ -0,0 +1,171 @@
+use crate::persistence::{DownloadHistory, DownloadRecord, DownloadStatus};
+use rusqlite::types::Value;
+use serde::Deserialize;
+
+/// Maximum page size accepted from the frontend
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadKind {
+    File,
+    Video,
+}
+
+#[derive(Clone, Copy, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Filename,
+    Size,
+    Status,
+}
+
+#[derive(Clone, Copy, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filters, sorting and paging for history queries. Every field is optional.
+#[derive(Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub statuses: Vec<DownloadStatus>,
+    pub kind: Option<DownloadKind>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub host: Option<String>,
+    pub filename: Option<String>,
+    // Video uploader, from the stored metadata
+    pub uploader: Option<String>,
+    // Video title, or the filename of downloads without one
+    pub title: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub sort_by: HistorySort,
+    pub order: SortOrder,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    /// WHERE clause and its parameters. Status and creation date use their
+    /// indexes; host has one of its own.
+    fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if !self.statuses.is_empty() {
+            conditions.push(format!("status IN ({})", vec!["?"; self.statuses.len()].join(", ")));
+            params.extend(self.statuses.iter().map(|s| Value::Text(s.as_str().to_string())));
+        }
+        match self.kind {
+            Some(DownloadKind::File) => conditions.push("is_video = 0".to_string()),
+            Some(DownloadKind::Video) => conditions.push("is_video = 1".to_string()),
+            None => {}
+        }
+        if let Some(t) = self.created_after {
+            conditions.push("created_at >= ?".to_string());
+            params.push(Value::Integer(t));
+        }
+        if let Some(t) = self.created_before {
+            conditions.push("created_at <= ?".to_string());
+            params.push(Value::Integer(t));
+        }
+        if let Some(s) = self.min_size {
+            conditions.push("total_size >= ?".to_string());
+            params.push(Value::Integer(s as i64));
+        }
+        if let Some(s) = self.max_size {
+            conditions.push("total_size <= ?".to_string());
+            params.push(Value::Integer(s as i64));
+        }
+        if let Some(needle) = non_empty(&self.filename) {
+            conditions.push("filename LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%{}%", escape_like(needle))));
+        }
+        if let Some(needle) = non_empty(&self.uploader) {
+            conditions.push("json_extract(video_metadata, '$.uploader') LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%{}%", escape_like(needle))));
+        }
+        if let Some(needle) = non_empty(&self.title) {
+            conditions.push(
+                "COALESCE(json_extract(video_metadata, '$.title'), filename) LIKE ? ESCAPE '\\'".to_string(),
+            );
+            params.push(Value::Text(format!("%{}%", escape_like(needle))));
+        }
+        if let Some(wanted) = non_empty(&self.host) {
+            // Match the host itself and any of its subdomains
+            let wanted = wanted.trim_start_matches("www.").to_lowercase();
+            conditions.push("(host LIKE ? ESCAPE '\\' OR host = ?)".to_string());
+            params.push(Value::Text(format!("%.{}", escape_like(&wanted))));
+            params.push(Value::Text(wanted));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), params)
+        } else {
+            (format!(" WHERE {}", conditions.join(" AND ")), params)
+        }
+    }
+
+    fn order_clause(&self) -> String {
+        let column = match self.sort_by {
+            HistorySort::CreatedAt => "created_at",
+            HistorySort::UpdatedAt => "updated_at",
+            HistorySort::Filename => "filename COLLATE NOCASE",
+            HistorySort::Size => "total_size",
+            HistorySort::Status => "status",
+        };
+        let order = match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        // Keep pages stable when the sort key ties
+        format!(" ORDER BY {} {}, id {}", column, order, order)
+    }
+}
+
+/// Trimmed text filter, or None when it is blank
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Escape LIKE wildcards so user input matches literally
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// One page of query results
+pub struct HistoryPage<'a> {
+    pub records: Vec<&'a DownloadRecord>,
+    pub total: usize,
+}
+
+impl DownloadHistory {
+    /// Run `query` against the history database. Pending writes are flushed
+    /// first so the results match the in-memory records they are read from.
+    pub async fn query(&self, query: &HistoryQuery) -> Result<HistoryPage<'_>, String> {
+        let db = self
+            .database()
+            .ok_or_else(|| "History database is not open".to_string())?;
+        self.flush().await?;
+
+        let (filter, params) = query.where_clause();
+        let order = query.order_clause();
+        let limit = query.limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let offset = query.offset;
+        let (ids, total) =
+            tokio::task::spawn_blocking(move || db.select_ids(&filter, &order, &params, limit, offset))
+                .await
+                .map_err(|e| format!("History task failed: {}", e))??;
+
+        let records = ids.iter().filter_map(|id| self.downloads.get(id)).collect();
+        Ok(HistoryPage { records, total })
+    }
+}
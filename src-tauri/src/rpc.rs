@@ -0,0 +1,599 @@
+use crate::commands::{
+    begin_download, cancel_download, cancel_video_download, pause_download,
+    pause_video_download, resume_download, resume_interrupted_download, start_queued_download,
+};
+use crate::downloader::{http_client, probe_url};
+use crate::persistence::{DownloadRecord, DownloadStatus};
+use crate::sites::is_video_url;
+use crate::state::{AppState, DownloadComplete, DownloadInfo, DownloadProgress};
+use crate::utils::{generate_unique_filename, sanitize_filename};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Port aria2 listens on, so existing front-ends find WDM without changes
+pub const DEFAULT_RPC_PORT: u16 = 6800;
+
+const RPC_PATH: &str = "/jsonrpc";
+
+/// Reported by `aria2.getVersion`; front-ends use it to check the connection
+const ARIA2_VERSION: &str = "1.37.0";
+
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// How long a client may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// JSON-RPC error codes; aria2 answers every failed call with code 1
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const ARIA2_ERROR: i64 = 1;
+
+/// Latest progress of running file downloads, for speeds in status replies
+static LIVE_PROGRESS: LazyLock<Mutex<HashMap<String, DownloadProgress>>> =
+    LazyLock::new(Default::default);
+
+/// The aria2-compatible JSON-RPC server on localhost
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RpcSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub secret: String, // clients send it as "token:<secret>", like aria2's --rpc-secret
+}
+
+impl Default for RpcSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_RPC_PORT,
+            secret: String::new(),
+        }
+    }
+}
+
+/// A random secret for clients to authenticate with, from the OS random source
+pub fn generate_secret() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate secret: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Listen on `127.0.0.1:<port>` and answer requests until the returned task is aborted
+pub async fn start_server(app: AppHandle, settings: RpcSettings) -> Result<JoinHandle<()>, String> {
+    if settings.secret.is_empty() {
+        return Err("The RPC server needs a secret".to_string());
+    }
+    let listener = TcpListener::bind(("127.0.0.1", settings.port))
+        .await
+        .map_err(|e| format!("Failed to listen on port {}: {}", settings.port, e))?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let app = app.clone();
+            let secret = settings.secret.clone();
+            tokio::spawn(async move {
+                let _ = handle_connection(&app, &secret, stream).await;
+            });
+        }
+    }))
+}
+
+/// Remember a progress event so `downloadSpeed` can be reported
+pub fn record_progress(progress: &DownloadProgress) {
+    if let Ok(mut live) = LIVE_PROGRESS.lock() {
+        live.insert(progress.id.clone(), progress.clone());
+    }
+}
+
+pub fn record_complete(complete: &DownloadComplete) {
+    if let Ok(mut live) = LIVE_PROGRESS.lock() {
+        live.remove(&complete.id);
+    }
+}
+
+/// aria2 identifies downloads by 16 hex digits; derive them from the history id
+fn gid_for(id: &str) -> String {
+    let digest = Sha256::digest(id.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compare without stopping at the first difference, so timing doesn't leak the secret
+fn secret_matches(given: &str, secret: &str) -> bool {
+    given.len() == secret.len()
+        && given
+            .bytes()
+            .zip(secret.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn handle_connection(app: &AppHandle, secret: &str, mut stream: TcpStream) -> Result<(), String> {
+    let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| "Request timed out".to_string())?;
+
+    let (status, body) = match request {
+        Ok((method, path, body)) => {
+            let path = path.split('?').next().unwrap_or_default();
+            match method.as_str() {
+                _ if path != RPC_PATH => ("404 Not Found", String::new()),
+                "OPTIONS" => ("204 No Content", String::new()),
+                "POST" => ("200 OK", handle_body(app, secret, &body).await.to_string()),
+                _ => ("405 Method Not Allowed", String::new()),
+            }
+        }
+        Err(e) => ("400 Bad Request", error_response(Value::Null, INVALID_REQUEST, &e).to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))?;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+/// Read one HTTP request, returning its method, path and body
+async fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>), String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEADER_SIZE {
+            return Err("Request headers too large".to_string());
+        }
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if n == 0 {
+            return Err("Connection closed".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()
+        .map_err(|_| "Invalid Content-Length".to_string())?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err("Request body too large".to_string());
+    }
+
+    let mut body = buf[header_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if n == 0 {
+            return Err("Connection closed".to_string());
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+    Ok((method, path, body))
+}
+
+/// Answer a single call or a batch
+async fn handle_body(app: &AppHandle, secret: &str, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e)),
+    };
+
+    match request {
+        Value::Array(calls) => {
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in calls {
+                responses.push(handle_call(app, secret, call).await);
+            }
+            Value::Array(responses)
+        }
+        call => handle_call(app, secret, call).await,
+    }
+}
+
+async fn handle_call(app: &AppHandle, secret: &str, call: Value) -> Value {
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = call.get("method").and_then(|m| m.as_str()) else {
+        return error_response(id, INVALID_REQUEST, "Invalid Request");
+    };
+
+    let mut params = match call.get("params") {
+        Some(Value::Array(params)) => params.clone(),
+        None => Vec::new(),
+        Some(_) => return error_response(id, INVALID_PARAMS, "params must be an array"),
+    };
+
+    // Like aria2, the secret comes first in the params as "token:<secret>"
+    let token = params
+        .first()
+        .and_then(|p| p.as_str())
+        .and_then(|p| p.strip_prefix("token:"))
+        .map(str::to_string);
+    match token {
+        Some(token) if secret_matches(&token, secret) => {
+            params.remove(0);
+        }
+        _ => return error_response(id, ARIA2_ERROR, "Unauthorized"),
+    }
+
+    let result = match method {
+        "aria2.addUri" => add_uri(app, &params).await,
+        "aria2.tellStatus" => tell_status(app, &params).await,
+        "aria2.pause" | "aria2.forcePause" => pause(app, &params).await,
+        "aria2.unpause" => unpause(app, &params).await,
+        "aria2.remove" | "aria2.forceRemove" => remove(app, &params).await,
+        "aria2.tellActive" => tell_active(app, &params).await,
+        "aria2.tellWaiting" => tell_waiting(app, &params).await,
+        "aria2.getGlobalStat" => global_stat(app).await,
+        "aria2.getVersion" => Ok(json!({ "version": ARIA2_VERSION, "enabledFeatures": [] })),
+        _ => return error_response(id, METHOD_NOT_FOUND, &format!("Method not found: {}", method)),
+    };
+
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, ARIA2_ERROR, &e),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn param_str(params: &[Value], index: usize, name: &str) -> Result<String, String> {
+    params
+        .get(index)
+        .and_then(|p| p.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("Missing {}", name))
+}
+
+/// `keys` limits which fields of a status are returned
+fn param_keys(params: &[Value], index: usize) -> Vec<String> {
+    params
+        .get(index)
+        .and_then(|p| p.as_array())
+        .map(|keys| keys.iter().filter_map(|k| k.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Find the history record a gid refers to
+async fn record_for(app: &AppHandle, gid: &str) -> Result<DownloadRecord, String> {
+    let state = app.state::<AppState>();
+    let history = state.history.read().await;
+    history
+        .downloads
+        .values()
+        .find(|r| gid_for(&r.id) == gid)
+        .cloned()
+        .ok_or_else(|| format!("GID {} is not found", gid))
+}
+
+/// aria2.addUri([uris], {out, dir, referer, header}) - mirrors are not supported, the first URI is used
+async fn add_uri(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let url = params
+        .first()
+        .and_then(|p| p.as_array())
+        .and_then(|uris| uris.iter().find_map(|u| u.as_str()))
+        .ok_or("Missing URIs")?
+        .to_string();
+    let options = params.get(1).and_then(|p| p.as_object()).cloned().unwrap_or_default();
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err("Only HTTP and HTTPS URIs are supported".to_string());
+    }
+    if is_video_url(&url) {
+        return Err("Video URLs must be added in the WDM app".to_string());
+    }
+
+    let download_dir = match option_str(&options, "dir") {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            if !dir.is_absolute() || !dir.is_dir() {
+                return Err(format!("{} is not a folder", dir.display()));
+            }
+            dir
+        }
+        None => {
+            let state = app.state::<AppState>();
+            let settings = state.settings.read().await;
+            settings.get_download_folder()
+        }
+    };
+
+    // aria2's `header` option is one "Name: value" line or a list of them
+    let mut headers: Vec<String> = match options.get("header") {
+        Some(Value::String(line)) => vec![line.clone()],
+        Some(Value::Array(lines)) => lines.iter().filter_map(|l| l.as_str().map(str::to_string)).collect(),
+        _ => Vec::new(),
+    };
+    if let Some(referer) = option_str(&options, "referer") {
+        headers.push(format!("Referer: {}", referer));
+    }
+
+    let info = {
+        let state = app.state::<AppState>();
+        let site_cookies = state.settings.read().await.site_cookies.clone();
+        let client = http_client(&site_cookies, &url, &headers).await?;
+        probe_url(&client, &url).await?
+    };
+    let mut filename = option_str(&options, "out")
+        .map(|out| sanitize_filename(&out))
+        .filter(|out| !out.is_empty())
+        .unwrap_or(info.filename);
+    if download_dir.join(&filename).exists() {
+        filename = generate_unique_filename(&download_dir, &filename);
+    }
+
+    let id = begin_download(
+        app,
+        info.url,
+        download_dir,
+        filename,
+        info.size.unwrap_or(0),
+        info.resumable,
+        headers,
+    )
+    .await?;
+
+    // The window didn't start this one, so tell it about the new download
+    let state = app.state::<AppState>();
+    if let Some(record) = state.history.read().await.get_download(&id) {
+        let _ = app.emit("download-added", DownloadInfo::from(record));
+    }
+    Ok(Value::String(gid_for(&id)))
+}
+
+/// aria2 option values are strings
+fn option_str(options: &Map<String, Value>, key: &str) -> Option<String> {
+    options.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+async fn tell_status(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let gid = param_str(params, 0, "GID")?;
+    let record = record_for(app, &gid).await?;
+    let running = is_running(app, &record.id).await;
+    Ok(status_json(&record, running, &param_keys(params, 1)))
+}
+
+async fn pause(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let gid = param_str(params, 0, "GID")?;
+    let record = record_for(app, &gid).await?;
+    if record.is_video {
+        pause_video_download(app.clone(), record.id).await?;
+    } else {
+        pause_download(app.clone(), record.id).await?;
+    }
+    Ok(Value::String(gid))
+}
+
+async fn unpause(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let gid = param_str(params, 0, "GID")?;
+    let record = record_for(app, &gid).await?;
+    let state = app.state::<AppState>();
+    let paused_in_place = state.downloads.read().await.contains_key(&record.id);
+
+    if paused_in_place {
+        resume_download(app.clone(), record.id).await?;
+    } else if record.status == DownloadStatus::Pending {
+        start_queued_download(app.clone(), record.id).await?;
+    } else {
+        resume_interrupted_download(app.clone(), record.id).await?;
+    }
+    Ok(Value::String(gid))
+}
+
+async fn remove(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let gid = param_str(params, 0, "GID")?;
+    let record = record_for(app, &gid).await?;
+
+    if is_running(app, &record.id).await {
+        if record.is_video {
+            cancel_video_download(app.clone(), record.id).await?;
+        } else {
+            cancel_download(app.clone(), record.id).await?;
+        }
+        return Ok(Value::String(gid));
+    }
+
+    // Nothing is running, so removing a waiting or paused download only updates its record
+    if !matches!(record.status, DownloadStatus::Pending | DownloadStatus::Paused) {
+        return Err(format!("GID {} cannot be removed now", gid));
+    }
+    let state = app.state::<AppState>();
+    state.history.write().await.update_download(&record.id, |r| {
+        r.status = DownloadStatus::Cancelled;
+    });
+    Ok(Value::String(gid))
+}
+
+async fn tell_active(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let keys = param_keys(params, 0);
+    let state = app.state::<AppState>();
+    let running = running_ids(app).await;
+    let history = state.history.read().await;
+
+    let mut records: Vec<&DownloadRecord> = history
+        .downloads
+        .values()
+        .filter(|r| r.status == DownloadStatus::Downloading && running.contains(&r.id))
+        .collect();
+    records.sort_by_key(|r| r.created_at);
+    Ok(Value::Array(records.into_iter().map(|r| status_json(r, true, &keys)).collect()))
+}
+
+/// aria2.tellWaiting(offset, num) - a negative offset counts from the end, newest first
+async fn tell_waiting(app: &AppHandle, params: &[Value]) -> Result<Value, String> {
+    let offset = params.first().and_then(|p| p.as_i64()).ok_or("Missing offset")?;
+    let num = params.get(1).and_then(|p| p.as_u64()).ok_or("Missing num")? as usize;
+    let keys = param_keys(params, 2);
+
+    let state = app.state::<AppState>();
+    let running = running_ids(app).await;
+    let history = state.history.read().await;
+
+    let mut records: Vec<&DownloadRecord> = history
+        .downloads
+        .values()
+        .filter(|r| !r.is_playlist && !running.contains(&r.id))
+        .filter(|r| {
+            matches!(
+                r.status,
+                DownloadStatus::Pending | DownloadStatus::Paused | DownloadStatus::Downloading
+            )
+        })
+        .collect();
+    records.sort_by_key(|r| r.created_at);
+
+    let start = if offset < 0 {
+        records.reverse();
+        (-offset - 1) as usize
+    } else {
+        offset as usize
+    };
+    Ok(Value::Array(
+        records
+            .into_iter()
+            .skip(start)
+            .take(num)
+            .map(|r| status_json(r, false, &keys))
+            .collect(),
+    ))
+}
+
+async fn global_stat(app: &AppHandle) -> Result<Value, String> {
+    let state = app.state::<AppState>();
+    let running = running_ids(app).await;
+    let history = state.history.read().await;
+
+    let (mut waiting, mut stopped) = (0, 0);
+    for record in history.downloads.values().filter(|r| !r.is_playlist) {
+        match record.status {
+            _ if running.contains(&record.id) => {}
+            DownloadStatus::Pending | DownloadStatus::Paused | DownloadStatus::Downloading => waiting += 1,
+            DownloadStatus::Completed | DownloadStatus::Failed | DownloadStatus::Cancelled => stopped += 1,
+        }
+    }
+
+    let speed: u64 = LIVE_PROGRESS
+        .lock()
+        .map(|live| {
+            live.values()
+                .filter(|p| running.contains(&p.id))
+                .map(|p| p.speed as u64)
+                .sum()
+        })
+        .unwrap_or(0);
+
+    Ok(json!({
+        "downloadSpeed": speed.to_string(),
+        "uploadSpeed": "0",
+        "numActive": running.len().to_string(),
+        "numWaiting": waiting.to_string(),
+        "numStopped": stopped.to_string(),
+        "numStoppedTotal": stopped.to_string(),
+    }))
+}
+
+/// Ids with a running file or video download
+async fn running_ids(app: &AppHandle) -> Vec<String> {
+    let state = app.state::<AppState>();
+    let mut ids: Vec<String> = state.downloads.read().await.keys().cloned().collect();
+    ids.extend(state.video_downloads.read().await.keys().cloned());
+    ids
+}
+
+async fn is_running(app: &AppHandle, id: &str) -> bool {
+    let state = app.state::<AppState>();
+    state.downloads.read().await.contains_key(id) || state.video_downloads.read().await.contains_key(id)
+}
+
+/// A record in aria2's status format, where every number is a string
+fn status_json(record: &DownloadRecord, running: bool, keys: &[String]) -> Value {
+    let live = LIVE_PROGRESS
+        .lock()
+        .ok()
+        .and_then(|live| live.get(&record.id).cloned())
+        .filter(|_| running);
+    let completed = live.as_ref().map(|p| p.downloaded).unwrap_or_else(|| record.total_downloaded());
+    let total = live.as_ref().map(|p| p.total).filter(|t| *t > 0).unwrap_or(record.total_size);
+    let speed = live.as_ref().map(|p| p.speed as u64).unwrap_or(0);
+
+    let status = match record.status {
+        DownloadStatus::Downloading if running => "active",
+        DownloadStatus::Downloading | DownloadStatus::Paused => "paused",
+        DownloadStatus::Pending => "waiting",
+        DownloadStatus::Completed => "complete",
+        DownloadStatus::Failed => "error",
+        DownloadStatus::Cancelled => "removed",
+    };
+    let path = PathBuf::from(&record.file_path);
+    let dir = path.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+
+    let mut status = json!({
+        "gid": gid_for(&record.id),
+        "status": status,
+        "totalLength": total.to_string(),
+        "completedLength": completed.to_string(),
+        "uploadLength": "0",
+        "downloadSpeed": speed.to_string(),
+        "uploadSpeed": "0",
+        "connections": if running { record.num_connections.to_string() } else { "0".to_string() },
+        "dir": dir,
+        "files": [{
+            "index": "1",
+            "path": record.file_path,
+            "length": total.to_string(),
+            "completedLength": completed.to_string(),
+            "selected": "true",
+            "uris": [{ "uri": record.url, "status": "used" }],
+        }],
+    });
+
+    if !keys.is_empty() {
+        if let Some(fields) = status.as_object_mut() {
+            fields.retain(|key, _| keys.contains(key));
+        }
+    }
+    status
+}
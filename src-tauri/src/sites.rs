@@ -0,0 +1,147 @@
+use crate::ytdlp::{is_ytdlp_installed, ytdlp_command};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::Duration;
+
+/// Video site URL patterns
+const VIDEO_PATTERNS: &[&str] = &[
+    r"youtube\.com/watch",
+    r"youtube\.com/shorts/",
+    r"youtube\.com/playlist\?",
+    r"youtu\.be/",
+    r"twitter\.com/.*/status/",
+    r"x\.com/.*/status/",
+    r"tiktok\.com/",
+    r"instagram\.com/(p|reel|reels)/",
+    r"vimeo\.com/",
+    r"twitch\.tv/",
+    r"dailymotion\.com/",
+    r"facebook\.com/.*/videos/",
+    r"reddit\.com/.*/comments/",
+    r"streamable\.com/",
+    r"v\.redd\.it/",
+];
+
+/// Playlist and channel URL patterns
+const PLAYLIST_PATTERNS: &[&str] = &[
+    r"youtube\.com/playlist\?",
+    r"youtube\.com/.*[?&]list=",
+    r"youtube\.com/(@[^/]+|channel/[^/]+|c/[^/]+)/?(videos|streams|shorts)?/?$",
+    r"vimeo\.com/(showcase|channels|album)/",
+    r"twitch\.tv/[^/]+/videos",
+    r"dailymotion\.com/playlist/",
+];
+
+/// How long a yt-dlp probe may take before the URL is treated as unsupported
+const PROBE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Probe results kept before the cache is cleared
+const PROBE_CACHE_LIMIT: usize = 512;
+
+static VIDEO_REGEXES: LazyLock<Vec<Regex>> = LazyLock::new(|| compile(VIDEO_PATTERNS));
+
+static PLAYLIST_REGEXES: LazyLock<Vec<Regex>> = LazyLock::new(|| compile(PLAYLIST_PATTERNS));
+
+/// Patterns added by the user in Settings
+static USER_REGEXES: RwLock<Vec<Regex>> = RwLock::new(Vec::new());
+
+/// Whether yt-dlp supports a URL, by URL
+static PROBE_CACHE: LazyLock<Mutex<HashMap<String, bool>>> = LazyLock::new(Default::default);
+
+/// How URLs are recognised as video pages
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct VideoDetectionSettings {
+    pub patterns: Vec<String>, // regexes matched against the whole URL
+    pub probe_ytdlp: bool,     // ask yt-dlp about URLs no pattern matches
+}
+
+fn compile(patterns: &[&str]) -> Vec<Regex> {
+    patterns.iter().filter_map(|p| Regex::new(p).ok()).collect()
+}
+
+/// Replace the user patterns, leaving them untouched if any fails to compile
+pub fn set_user_patterns(patterns: &[String]) -> Result<(), String> {
+    let regexes = patterns
+        .iter()
+        .map(|p| Regex::new(p).map_err(|e| format!("Invalid pattern {}: {}", p, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Ok(mut user) = USER_REGEXES.write() {
+        *user = regexes;
+    }
+    Ok(())
+}
+
+/// Check if a URL is a video site URL
+pub fn is_video_url(url: &str) -> bool {
+    if VIDEO_REGEXES.iter().any(|re| re.is_match(url)) {
+        return true;
+    }
+    USER_REGEXES
+        .read()
+        .map(|user| user.iter().any(|re| re.is_match(url)))
+        .unwrap_or(false)
+}
+
+/// Check if a URL points at a playlist or channel rather than a single video
+pub fn is_playlist_url(url: &str) -> bool {
+    PLAYLIST_REGEXES.iter().any(|re| re.is_match(url))
+}
+
+/// Ask yt-dlp whether it can extract a video from `url`. Results are cached.
+pub async fn probe_video_url(ytdlp: &[String], url: &str, extra_args: &[String]) -> bool {
+    if let Some(supported) = PROBE_CACHE.lock().ok().and_then(|c| c.get(url).copied()) {
+        return supported;
+    }
+
+    let supported = run_probe(ytdlp, url, extra_args).await;
+
+    if let Ok(mut cache) = PROBE_CACHE.lock() {
+        if cache.len() >= PROBE_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(url.to_string(), supported);
+    }
+    supported
+}
+
+/// Forget probe results, e.g. after yt-dlp or its arguments change
+pub fn clear_probe_cache() {
+    if let Ok(mut cache) = PROBE_CACHE.lock() {
+        cache.clear();
+    }
+}
+
+async fn run_probe(ytdlp: &[String], url: &str, extra_args: &[String]) -> bool {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return false;
+    }
+
+    if !is_ytdlp_installed(ytdlp) {
+        return false;
+    }
+
+    let probe = ytdlp_command(ytdlp)
+        .args(["--simulate", "--no-playlist", "--no-warnings"])
+        .args(["--print", "%(extractor_key)s %(direct)s"])
+        .args(extra_args)
+        .arg(url)
+        .kill_on_drop(true)
+        .output();
+
+    let Ok(Ok(output)) = tokio::time::timeout(PROBE_TIMEOUT, probe).await else {
+        return false;
+    };
+    if !output.status.success() {
+        return false;
+    }
+
+    // The generic extractor also "supports" plain file links; those belong to the file downloader
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.lines().next().unwrap_or_default().split_whitespace();
+    let extractor = fields.next().unwrap_or_default();
+    let direct = fields.next() == Some("True");
+    !extractor.is_empty() && !direct
+}
@@ -1,5 +1,12 @@
-use crate::persistence::DownloadHistory;
-use crate::video::VideoDownloadHandle;
+use crate::cookies::{find_site_cookies, ytdlp_cookie_args, SiteCookies};
+use crate::migrations::{
+    backup_newer_file, document_version, migrate_settings, stamp_version, SETTINGS_VERSION,
+};
+use crate::persistence::{DownloadHistory, DownloadRecord};
+use crate::rpc::RpcSettings;
+use crate::sites::VideoDetectionSettings;
+use crate::video::{EmbedOptions, VideoDownloadHandle, VideoMetadata, DEFAULT_OUTPUT_TEMPLATE};
+use crate::ytdlp::{validate_version, YtdlpChannel, YtdlpRelease};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -14,7 +21,8 @@ pub struct AppState {
     pub downloads: RwLock<HashMap<String, Arc<DownloadHandle>>>,
     pub video_downloads: RwLock<HashMap<String, Arc<VideoDownloadHandle>>>,
     pub settings: RwLock<Settings>,
-    pub history: RwLock<DownloadHistory>,
+    pub history: Arc<RwLock<DownloadHistory>>, // shared with the download engine
+    pub rpc_server: RwLock<Option<tokio::task::JoinHandle<()>>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -23,6 +31,69 @@ pub struct Settings {
     pub download_folder: Option<String>,
     #[serde(default)]
     pub speed_limit: u64, // bytes per second, 0 = unlimited
+    #[serde(default)]
+    pub ffmpeg_path: Option<String>, // None = managed install or PATH
+    #[serde(default)]
+    pub ytdlp_update: YtdlpUpdateSettings,
+    #[serde(default)]
+    pub ytdlp_command: Vec<String>, // program and leading arguments; empty = managed binary
+    #[serde(default)]
+    pub ytdlp_extra_args: Vec<String>, // added to every yt-dlp invocation
+    #[serde(default)]
+    pub site_cookies: Vec<SiteCookies>,
+    #[serde(default = "default_output_template")]
+    pub output_template: String, // yt-dlp template, relative to the download folder
+    #[serde(default)]
+    pub video_detection: VideoDetectionSettings,
+    #[serde(default)]
+    pub embed: EmbedOptions, // default for video downloads, overridable per download
+    #[serde(default)]
+    pub rpc: RpcSettings,
+}
+
+fn default_output_template() -> String {
+    DEFAULT_OUTPUT_TEMPLATE.to_string()
+}
+
+// How the managed yt-dlp binary is kept up to date
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YtdlpUpdateSettings {
+    pub channel: YtdlpChannel,
+    pub pinned_version: Option<String>, // None = latest on the channel
+    pub auto_update: bool,
+    pub interval_hours: u64,
+    pub last_check: i64, // unix seconds
+}
+
+impl Default for YtdlpUpdateSettings {
+    fn default() -> Self {
+        Self {
+            channel: YtdlpChannel::Stable,
+            pinned_version: None,
+            auto_update: false,
+            interval_hours: 24,
+            last_check: 0,
+        }
+    }
+}
+
+impl YtdlpUpdateSettings {
+    /// The release to install. settings.json may have been edited by hand, so
+    /// the pinned version is checked again here.
+    pub fn release(&self) -> Result<YtdlpRelease, String> {
+        if let Some(version) = &self.pinned_version {
+            validate_version(version)?;
+        }
+        Ok(YtdlpRelease {
+            channel: self.channel,
+            version: self.pinned_version.clone(),
+        })
+    }
+
+    pub fn is_check_due(&self, now: i64) -> bool {
+        self.auto_update && now - self.last_check >= self.interval_hours as i64 * 3600
+    }
 }
 
 impl Default for Settings {
@@ -31,6 +102,15 @@ impl Default for Settings {
             connections: DEFAULT_CONNECTIONS,
             download_folder: None,
             speed_limit: 0,
+            ffmpeg_path: None,
+            ytdlp_update: YtdlpUpdateSettings::default(),
+            ytdlp_command: Vec::new(),
+            ytdlp_extra_args: Vec::new(),
+            site_cookies: Vec::new(),
+            output_template: default_output_template(),
+            video_detection: VideoDetectionSettings::default(),
+            embed: EmbedOptions::default(),
+            rpc: RpcSettings::default(),
         }
     }
 }
@@ -48,10 +128,18 @@ impl Settings {
         if !path.exists() {
             return Self::default();
         }
-        match tokio::fs::read_to_string(&path).await {
-            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-            Err(_) => Self::default(),
-        }
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        let mut value: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(value) => value,
+            Err(_) => return Self::default(),
+        };
+
+        backup_newer_file(&path, document_version(&value), SETTINGS_VERSION).await;
+        migrate_settings(&mut value);
+        serde_json::from_value(value).unwrap_or_default()
     }
 
     pub async fn save(&self) -> Result<(), String> {
@@ -61,7 +149,10 @@ impl Settings {
                 .await
                 .map_err(|e| format!("Failed to create settings directory: {}", e))?;
         }
-        let content = serde_json::to_string_pretty(self)
+        let mut value = serde_json::to_value(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        stamp_version(&mut value, SETTINGS_VERSION);
+        let content = serde_json::to_string_pretty(&value)
             .map_err(|e| format!("Failed to serialize settings: {}", e))?;
         tokio::fs::write(&path, content)
             .await
@@ -69,6 +160,15 @@ impl Settings {
         Ok(())
     }
 
+    /// Per-URL yt-dlp arguments: the site's cookies, then the user's extra arguments
+    pub fn ytdlp_args(&self, url: &str) -> Vec<String> {
+        let mut args = find_site_cookies(&self.site_cookies, url)
+            .map(ytdlp_cookie_args)
+            .unwrap_or_default();
+        args.extend(self.ytdlp_extra_args.iter().cloned());
+        args
+    }
+
     pub fn get_download_folder(&self) -> PathBuf {
         self.download_folder
             .as_ref()
@@ -85,6 +185,23 @@ pub struct DownloadHandle {
     pub speed_limit: AtomicU64, // bytes per second, 0 = unlimited
 }
 
+impl DownloadHandle {
+    /// Handle for a history record, starting from its saved chunk progress
+    pub fn for_record(record: &DownloadRecord, speed_limit: u64) -> Self {
+        Self {
+            id: record.id.clone(),
+            cancelled: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            chunk_downloaded: record
+                .chunks
+                .iter()
+                .map(|c| Arc::new(AtomicU64::new(c.downloaded)))
+                .collect(),
+            speed_limit: AtomicU64::new(speed_limit),
+        }
+    }
+}
+
 #[derive(Clone, Serialize)]
 pub struct UrlInfo {
     pub url: String,
@@ -141,5 +258,49 @@ pub struct DownloadInfo {
     pub downloaded: u64,
     pub status: String,
     pub resumable: bool,
+    pub is_video: bool,
+    pub parent_id: Option<String>,
+    pub is_playlist: bool,
+    pub subtitle_files: Vec<String>,
+    pub thumbnail: Option<String>, // remote URL, or the cached copy once saved
+    pub video_metadata: Option<VideoMetadata>,
     pub created_at: i64,
 }
+
+impl From<&DownloadRecord> for DownloadInfo {
+    fn from(r: &DownloadRecord) -> Self {
+        Self {
+            id: r.id.clone(),
+            url: r.url.clone(),
+            filename: r.filename.clone(),
+            file_path: r.file_path.clone(),
+            total_size: r.total_size,
+            downloaded: r.total_downloaded(),
+            status: format!("{:?}", r.status),
+            resumable: r.resumable,
+            is_video: r.is_video,
+            parent_id: r.parent_id.clone(),
+            is_playlist: r.is_playlist,
+            subtitle_files: r.subtitle_files.clone(),
+            thumbnail: r.thumbnail.clone(),
+            video_metadata: r.video_metadata.clone(),
+            created_at: r.created_at,
+        }
+    }
+}
+
+// Installed yt-dlp compared with the newest release
+#[derive(Clone, Serialize)]
+pub struct YtdlpUpdateCheck {
+    pub current: Option<String>,
+    pub latest: String,
+    pub update_available: bool,
+}
+
+// One page of history query results
+#[derive(Clone, Serialize)]
+pub struct HistoryPageInfo {
+    pub downloads: Vec<DownloadInfo>,
+    pub total: usize,
+    pub offset: usize,
+}
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Image types we keep, by Content-Type
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/webp", "webp"),
+    ("image/gif", "gif"),
+];
+
+/// Get the directory where video thumbnails are cached
+pub fn get_thumbnails_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wdm")
+        .join("thumbnails")
+}
+
+/// Download a thumbnail into the cache as `<id>.<ext>` and return its path
+pub async fn cache_thumbnail(client: &reqwest::Client, url: &str, id: &str) -> Result<PathBuf, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download thumbnail: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to download thumbnail: HTTP {}", response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    let ext = IMAGE_TYPES
+        .iter()
+        .find(|(mime, _)| content_type.starts_with(mime))
+        .map(|(_, ext)| *ext)
+        .ok_or_else(|| format!("Thumbnail is not an image: {}", content_type))?;
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download thumbnail: {}", e))?;
+
+    let dir = get_thumbnails_dir();
+    fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create thumbnails directory: {}", e))?;
+
+    let path = dir.join(format!("{}.{}", id, ext));
+    fs::write(&path, &bytes)
+        .await
+        .map_err(|e| format!("Failed to save thumbnail: {}", e))?;
+    Ok(path)
+}
+
+/// Delete the cached `<id>.<ext>` thumbnail of download `id`. Only the file
+/// name of `thumbnail` is used, so remote URLs and paths outside the cache
+/// can't point the delete anywhere else.
+pub async fn remove_thumbnail(id: &str, thumbnail: Option<&str>) {
+    let Some(name) = thumbnail.and_then(|t| Path::new(t).file_name()) else {
+        return;
+    };
+    let expected = IMAGE_TYPES
+        .iter()
+        .any(|(_, ext)| name.to_str() == Some(format!("{}.{}", id, ext).as_str()));
+    if expected {
+        let _ = fs::remove_file(get_thumbnails_dir().join(name)).await;
+    }
+}
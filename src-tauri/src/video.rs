@@ -1,46 +1,24 @@
-use crate::ytdlp::get_ytdlp_path;
-use crate::state::AppState;
+use crate::downloader::{DownloadContext, EventSink};
+use crate::ffmpeg::find_ffmpeg;
+use crate::formats::{FormatPreference, FormatSelector};
+use crate::logs::DownloadLog;
+use crate::persistence::{DownloadHistory, DownloadRecord, DownloadStatus};
+use crate::utils::sanitize_filename;
+use crate::ytdlp::{is_ytdlp_installed, ytdlp_command};
+use crate::state::Settings;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
+use tokio::process::Child;
 use tokio::sync::Mutex;
 
-/// Video site URL patterns
-const VIDEO_PATTERNS: &[&str] = &[
-    r"youtube\.com/watch",
-    r"youtube\.com/shorts/",
-    r"youtu\.be/",
-    r"twitter\.com/.*/status/",
-    r"x\.com/.*/status/",
-    r"tiktok\.com/",
-    r"instagram\.com/(p|reel|reels)/",
-    r"vimeo\.com/",
-    r"twitch\.tv/",
-    r"dailymotion\.com/",
-    r"facebook\.com/.*/videos/",
-    r"reddit\.com/.*/comments/",
-    r"streamable\.com/",
-    r"v\.redd\.it/",
-];
-
-/// Check if a URL is a video site URL
-pub fn is_video_url(url: &str) -> bool {
-    for pattern in VIDEO_PATTERNS {
-        if let Ok(re) = Regex::new(pattern) {
-            if re.is_match(url) {
-                return true;
-            }
-        }
-    }
-    false
-}
+/// stderr lines kept to explain a failure that printed no `ERROR:` line
+const STDERR_TAIL_LINES: usize = 20;
 
 /// Video format information from yt-dlp
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +46,154 @@ pub struct VideoInfo {
     pub view_count: Option<u64>,
     pub formats: Vec<VideoFormat>,
     pub best_format: Option<String>,
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleTrack>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    /// yt-dlp's "is_live", "is_upcoming", "was_live", ...
+    #[serde(default)]
+    pub live_status: Option<String>,
+}
+
+/// A chapter of a video, with times in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    #[serde(default)]
+    pub title: String,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// A subtitle language reported by yt-dlp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleTrack {
+    pub lang: String,
+    pub name: Option<String>,
+    /// File formats the site offers for this language (vtt, srt, ...)
+    pub formats: Vec<String>,
+    /// True for automatically generated captions
+    pub automatic: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// Subtitle choices for a video download
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SubtitleOptions {
+    pub languages: Vec<String>,
+    pub format: SubtitleFormat,
+    /// Embed the subtitles into the video container as well
+    pub embed: bool,
+    /// Fall back to automatic captions for languages without real subtitles
+    pub include_automatic: bool,
+}
+
+/// A single entry of a playlist, as listed by `--flat-playlist`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub index: u64,
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub duration: Option<f64>,
+    pub uploader: Option<String>,
+}
+
+/// Playlist or channel information from yt-dlp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistInfo {
+    pub url: String,
+    pub id: Option<String>,
+    pub title: String,
+    pub uploader: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// Fields of yt-dlp's progress hook, printed by our `%(progress)j` template
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct RawProgress {
+    status: Option<String>,
+    filename: Option<String>,
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+    elapsed: Option<f64>,
+    fragment_index: Option<f64>,
+    fragment_count: Option<f64>,
+}
+
+/// Details yt-dlp reports about a finished video
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct VideoMetadata {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub duration: Option<f64>,
+    pub ext: Option<String>,
+    // The fields below are only known when an info JSON was written
+    pub channel: Option<String>,
+    /// YYYYMMDD
+    pub upload_date: Option<String>,
+    pub tags: Vec<String>,
+    pub webpage_url: Option<String>,
+    /// Path of the `.info.json` written next to the video
+    pub info_json: Option<String>,
+}
+
+/// One line of the `--print-to-file after_move` output
+#[derive(Debug, Deserialize)]
+struct FinalFile {
+    filepath: String,
+    #[serde(flatten)]
+    metadata: VideoMetadata,
+    /// Chapters, with the file each was split into when `--split-chapters` ran
+    #[serde(default)]
+    chapters: Option<Vec<ChapterFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChapterFile {
+    #[serde(default)]
+    filepath: Option<String>,
+}
+
+/// Index the `.info.json` yt-dlp wrote for `video_path`, if there is one
+async fn read_info_json(video_path: &str) -> Option<VideoMetadata> {
+    let path = Path::new(video_path).with_extension("info.json");
+    let content = tokio::fs::read_to_string(&path).await.ok()?;
+    let mut metadata: VideoMetadata = serde_json::from_str(&content).ok()?;
+    metadata.info_json = Some(path.to_string_lossy().to_string());
+    Some(metadata)
+}
+
+/// Read the last file yt-dlp reported as moved into place
+async fn read_result_file(path: &Path) -> Option<FinalFile> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    content
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<FinalFile>(line).ok())
+        .filter(|f| !f.filepath.is_empty())
 }
 
 /// Progress information for video download
@@ -80,14 +206,74 @@ pub struct VideoProgress {
     pub total_bytes: u64,
     pub speed: f64,
     pub eta: Option<u64>,
+    /// Seconds since the download started
+    pub elapsed: Option<f64>,
     pub filename: String,
 }
 
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+#[cfg(windows)]
+const CTRL_C_EVENT: u32 = 0;
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn AttachConsole(process_id: u32) -> i32;
+    fn FreeConsole() -> i32;
+    fn SetConsoleCtrlHandler(handler: Option<unsafe extern "system" fn(u32) -> i32>, add: i32) -> i32;
+    fn GenerateConsoleCtrlEvent(ctrl_event: u32, process_group_id: u32) -> i32;
+}
+
+/// Swallows the Ctrl+C WDM sends while attached to yt-dlp's console. Unlike
+/// ignoring Ctrl+C outright, a handler isn't inherited by later yt-dlp runs.
+#[cfg(windows)]
+unsafe extern "system" fn ignore_ctrl_c(_ctrl_type: u32) -> i32 {
+    1
+}
+
+/// Attaching to a console is process-wide, so one stop at a time
+#[cfg(windows)]
+static CONSOLE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Send Ctrl+C to yt-dlp and its ffmpeg. Release builds have no console to
+/// send from, so WDM attaches to yt-dlp's hidden one for the call.
+#[cfg(windows)]
+fn send_ctrl_c(pid: u32) -> Result<(), String> {
+    static HANDLER: std::sync::Once = std::sync::Once::new();
+    let _guard = CONSOLE_LOCK.lock().map_err(|_| "Console lock poisoned".to_string())?;
+
+    // SAFETY: plain Win32 calls; the handler is a static function
+    unsafe {
+        FreeConsole();
+        if AttachConsole(pid) == 0 {
+            return Err(format!(
+                "Failed to attach to yt-dlp's console: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        HANDLER.call_once(|| {
+            SetConsoleCtrlHandler(Some(ignore_ctrl_c), 1);
+        });
+        let sent = GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0);
+        let error = std::io::Error::last_os_error();
+        FreeConsole();
+        if sent == 0 {
+            return Err(format!("Failed to signal yt-dlp: {}", error));
+        }
+    }
+    Ok(())
+}
+
 /// Handle for a video download process
 pub struct VideoDownloadHandle {
     #[allow(dead_code)]
     pub id: String,
     pub cancelled: AtomicBool,
+    pub paused: AtomicBool,
+    /// Set when a live recording is being stopped and should be kept
+    pub stopping: AtomicBool,
     pub process: Mutex<Option<Child>>,
 }
 
@@ -96,27 +282,299 @@ impl VideoDownloadHandle {
         Self {
             id,
             cancelled: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            stopping: AtomicBool::new(false),
             process: Mutex::new(None),
         }
     }
+
+    /// Ask yt-dlp to stop the way Ctrl+C would, so it finalizes what it has
+    /// recorded instead of leaving a half-written file
+    pub async fn interrupt(&self) -> Result<(), String> {
+        let process = self.process.lock().await;
+        let pid = process
+            .as_ref()
+            .and_then(|c| c.id())
+            .ok_or("yt-dlp is not running")?;
+
+        #[cfg(unix)]
+        {
+            let status = tokio::process::Command::new("kill")
+                .args(["-INT", &pid.to_string()])
+                .status()
+                .await
+                .map_err(|e| format!("Failed to signal yt-dlp: {}", e))?;
+            if !status.success() {
+                return Err("Failed to signal yt-dlp".to_string());
+            }
+        }
+
+        #[cfg(windows)]
+        send_ctrl_c(pid)?;
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = pid;
+            return Err("Stopping a recording is not supported on this platform".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Stop the yt-dlp process; the download loop notices the flags that were set
+    pub async fn kill(&self) {
+        let mut process = self.process.lock().await;
+        if let Some(child) = process.as_mut() {
+            let _ = child.kill().await;
+        }
+    }
 }
 
-/// Fetch video information using yt-dlp
-pub async fn fetch_video_info(url: &str) -> Result<VideoInfo, String> {
-    let ytdlp_path = get_ytdlp_path();
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    M4a,
+    Opus,
+}
 
-    if !ytdlp_path.exists() {
+impl AudioFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Opus => "opus",
+        }
+    }
+}
+
+/// Audio-only extraction settings (needs ffmpeg)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AudioOptions {
+    pub format: AudioFormat,
+    /// VBR quality from "0" (best) to "10", or a bitrate such as "192K"
+    pub quality: Option<String>,
+    pub embed_thumbnail: bool,
+    pub embed_metadata: bool,
+}
+
+/// Per-download choices sent with `start_video_download`
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct VideoDownloadRequest {
+    pub subtitles: Option<SubtitleOptions>,
+    pub audio: Option<AudioOptions>,
+    pub format_preference: Option<FormatPreference>,
+    /// Overrides the output template from Settings
+    pub output_template: Option<String>,
+    pub live: Option<LiveOptions>,
+    pub chapters: Option<ChapterOptions>,
+    /// Overrides the embed options from Settings
+    pub embed: Option<EmbedOptions>,
+    /// Details already fetched with `get_video_info`, saving a second lookup
+    pub info: Option<VideoInfo>,
+}
+
+/// What yt-dlp writes into or next to the downloaded file
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct EmbedOptions {
+    pub metadata: bool,
+    pub thumbnail: bool,
+    pub chapters: bool,
+    /// Write a `.info.json` next to the file; it is also indexed into history
+    pub info_json: bool,
+}
+
+impl EmbedOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Embedding runs through ffmpeg; writing the info JSON does not
+    pub fn needs_ffmpeg(&self) -> bool {
+        self.metadata || self.thumbnail || self.chapters
+    }
+}
+
+/// Live-stream recording settings
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct LiveOptions {
+    /// Record from the start of the stream instead of from now
+    pub from_start: bool,
+    /// Wait for a scheduled stream to begin, retrying every this many seconds
+    pub wait_for_video: Option<u64>,
+}
+
+/// SponsorBlock categories yt-dlp understands
+const SPONSORBLOCK_CATEGORIES: &[&str] = &[
+    "sponsor", "intro", "outro", "selfpromo", "preview", "filler", "interaction",
+    "music_offtopic", "poi_highlight", "chapter", "all",
+];
+
+/// Categories that only mark a point or a chapter and can't be cut out
+const SPONSORBLOCK_MARK_ONLY: &[&str] = &["poi_highlight", "chapter"];
+
+/// File name for split chapters, placed next to the video
+const CHAPTER_TEMPLATE: &str = "%(title)s - %(section_number)03d %(section_title)s.%(ext)s";
+
+/// A time range to download, in seconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Chapter splitting, time ranges and SponsorBlock handling
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ChapterOptions {
+    /// Also write each chapter to its own file
+    pub split: bool,
+    /// Download only these parts of the video
+    pub sections: Vec<TimeRange>,
+    /// SponsorBlock categories to mark as chapters
+    pub sponsorblock_mark: Vec<String>,
+    /// SponsorBlock categories to cut out
+    pub sponsorblock_remove: Vec<String>,
+}
+
+impl ChapterOptions {
+    pub fn is_empty(&self) -> bool {
+        !self.split
+            && self.sections.is_empty()
+            && self.sponsorblock_mark.is_empty()
+            && self.sponsorblock_remove.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for range in &self.sections {
+            if range.start < 0.0 || range.end <= range.start {
+                return Err(format!("Invalid time range: {}-{}", range.start, range.end));
+            }
+        }
+        for category in &self.sponsorblock_mark {
+            if !SPONSORBLOCK_CATEGORIES.contains(&category.as_str()) {
+                return Err(format!("Unknown SponsorBlock category: {}", category));
+            }
+        }
+        for category in &self.sponsorblock_remove {
+            if !SPONSORBLOCK_CATEGORIES.contains(&category.as_str())
+                || SPONSORBLOCK_MARK_ONLY.contains(&category.as_str())
+            {
+                return Err(format!("SponsorBlock category can't be removed: {}", category));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output template used when Settings don't name one
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// Reject output templates that could write outside the download folder
+pub fn validate_output_template(template: &str) -> Result<(), String> {
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("Output template is empty".to_string());
+    }
+    let has_drive = template.chars().nth(1) == Some(':');
+    if Path::new(template).is_absolute() || template.starts_with(['/', '\\']) || has_drive {
+        return Err("Output template must be relative to the download folder".to_string());
+    }
+    if template.split(['/', '\\']).any(|part| part.trim() == "..") {
+        return Err("Output template must not contain '..'".to_string());
+    }
+    if !template.contains("%(ext)s") {
+        return Err("Output template must include %(ext)s".to_string());
+    }
+    Ok(())
+}
+
+/// Fill in playlist fields ourselves, since each entry is downloaded on its own
+/// with `--no-playlist` and yt-dlp wouldn't know them
+pub fn fill_playlist_fields(template: &str, index: u64, count: usize, title: &str, id: Option<&str>) -> String {
+    let re = Regex::new(r"%\((playlist_index|playlist_autonumber|playlist_count|playlist_title|playlist_id|playlist)\)(0?)(\d*)([sd])")
+        .expect("valid playlist field pattern");
+    re.replace_all(template, |caps: &regex::Captures| {
+        let number = match &caps[1] {
+            "playlist_index" | "playlist_autonumber" => Some(index),
+            "playlist_count" => Some(count as u64),
+            _ => None,
+        };
+        let value = match number {
+            Some(n) => {
+                let width: usize = caps[3].parse().unwrap_or(0);
+                if &caps[2] == "0" {
+                    format!("{:0width$}", n, width = width)
+                } else {
+                    format!("{:width$}", n, width = width)
+                }
+            }
+            None if &caps[1] == "playlist_id" => sanitize_filename(id.unwrap_or(title)),
+            None => sanitize_filename(title),
+        };
+        // The result is still a template, so keep literal percent signs literal
+        value.replace('%', "%%")
+    })
+    .to_string()
+}
+
+/// Per-download yt-dlp options, kept on the history record so the same
+/// download can be run again on resume
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct VideoOptions {
+    pub format_id: String,
+    /// Full output path template passed to `-o`
+    pub output_template: String,
+    pub subtitles: Option<SubtitleOptions>,
+    /// Extract audio only instead of keeping the video
+    pub audio: Option<AudioOptions>,
+    /// `-S` sort keys from a format preference
+    pub format_sort: Vec<String>,
+    pub merge_output_format: Option<String>,
+    /// Record a live stream rather than download a finished video
+    pub live: Option<LiveOptions>,
+    pub chapters: Option<ChapterOptions>,
+    pub embed: Option<EmbedOptions>,
+}
+
+impl VideoOptions {
+    /// `template` is a yt-dlp output template relative to `output_dir`
+    pub fn new(format_id: String, output_dir: &Path, template: &str) -> Self {
+        Self {
+            format_id,
+            output_template: output_dir.join(template).to_string_lossy().to_string(),
+            subtitles: None,
+            audio: None,
+            format_sort: Vec::new(),
+            merge_output_format: None,
+            live: None,
+            chapters: None,
+            embed: None,
+        }
+    }
+}
+
+/// Fetch video information using yt-dlp
+pub async fn fetch_video_info(ytdlp: &[String], url: &str, extra_args: &[String]) -> Result<VideoInfo, String> {
+    if !is_ytdlp_installed(ytdlp) {
         return Err("yt-dlp not installed".to_string());
     }
 
-    let output = Command::new(&ytdlp_path)
+    let output = ytdlp_command(ytdlp)
         .args([
             "--dump-json",
             "--no-download",
             "--no-warnings",
             "--no-playlist",
-            url,
         ])
+        .args(extra_args)
+        .arg(url)
         .output()
         .await
         .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
@@ -131,34 +589,11 @@ pub async fn fetch_video_info(url: &str) -> Result<VideoInfo, String> {
         serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
     // Parse formats
-    let formats: Vec<VideoFormat> = if let Some(formats_arr) = json.get("formats").and_then(|f| f.as_array()) {
-        formats_arr
-            .iter()
-            .filter_map(|f| {
-                let format_id = f.get("format_id")?.as_str()?.to_string();
-                let ext = f.get("ext").and_then(|e| e.as_str()).unwrap_or("mp4").to_string();
-
-                // Skip formats without video or audio
-                let vcodec = f.get("vcodec").and_then(|v| v.as_str()).map(|s| s.to_string());
-                let acodec = f.get("acodec").and_then(|a| a.as_str()).map(|s| s.to_string());
-
-                Some(VideoFormat {
-                    format_id,
-                    ext,
-                    resolution: f.get("resolution").and_then(|r| r.as_str()).map(|s| s.to_string()),
-                    filesize: f.get("filesize").and_then(|s| s.as_u64()),
-                    filesize_approx: f.get("filesize_approx").and_then(|s| s.as_u64()),
-                    vcodec,
-                    acodec,
-                    fps: f.get("fps").and_then(|fps| fps.as_f64()),
-                    tbr: f.get("tbr").and_then(|tbr| tbr.as_f64()),
-                    format_note: f.get("format_note").and_then(|n| n.as_str()).map(|s| s.to_string()),
-                })
-            })
-            .collect()
-    } else {
-        Vec::new()
-    };
+    let formats: Vec<VideoFormat> = json
+        .get("formats")
+        .and_then(|f| f.as_array())
+        .map(|formats_arr| formats_arr.iter().filter_map(parse_format).collect())
+        .unwrap_or_default();
 
     // Filter and simplify formats for the UI
     let simplified_formats = simplify_formats(&formats);
@@ -182,6 +617,301 @@ pub async fn fetch_video_info(url: &str) -> Result<VideoInfo, String> {
         view_count: json.get("view_count").and_then(|v| v.as_u64()),
         formats: simplified_formats,
         best_format: Some("best".to_string()),
+        subtitles: parse_subtitles(&json),
+        chapters: json
+            .get("chapters")
+            .and_then(|c| serde_json::from_value(c.clone()).ok())
+            .unwrap_or_default(),
+        live_status: json
+            .get("live_status")
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_string()),
+    })
+}
+
+/// Parse one entry of yt-dlp's `formats` list
+fn parse_format(f: &serde_json::Value) -> Option<VideoFormat> {
+    let format_id = f.get("format_id")?.as_str()?.to_string();
+    let ext = f.get("ext").and_then(|e| e.as_str()).unwrap_or("mp4").to_string();
+
+    // Skip formats without video or audio
+    let vcodec = f.get("vcodec").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let acodec = f.get("acodec").and_then(|a| a.as_str()).map(|s| s.to_string());
+
+    Some(VideoFormat {
+        format_id,
+        ext,
+        resolution: f.get("resolution").and_then(|r| r.as_str()).map(|s| s.to_string()),
+        filesize: f.get("filesize").and_then(|s| s.as_u64()),
+        filesize_approx: f.get("filesize_approx").and_then(|s| s.as_u64()),
+        vcodec,
+        acodec,
+        fps: f.get("fps").and_then(|fps| fps.as_f64()),
+        tbr: f.get("tbr").and_then(|tbr| tbr.as_f64()),
+        format_note: f.get("format_note").and_then(|n| n.as_str()).map(|s| s.to_string()),
+    })
+}
+
+/// Ask yt-dlp which formats a selector picks for `url`, without downloading
+pub async fn resolve_formats(
+    ytdlp: &[String],
+    url: &str,
+    selector: &FormatSelector,
+    extra_args: &[String],
+) -> Result<Vec<VideoFormat>, String> {
+    if !is_ytdlp_installed(ytdlp) {
+        return Err("yt-dlp not installed".to_string());
+    }
+
+    let mut cmd = ytdlp_command(ytdlp);
+    cmd.args(["--dump-json", "--no-download", "--no-warnings", "--no-playlist"])
+        .args(["-f", &selector.format]);
+    if !selector.sort.is_empty() {
+        cmd.args(["-S", &selector.sort.join(",")]);
+    }
+    let output = cmd
+        .args(extra_args)
+        .arg(url)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+    // Merged downloads list their parts; single formats are described at the top level
+    match json.get("requested_formats").and_then(|f| f.as_array()) {
+        Some(requested) => Ok(requested.iter().filter_map(parse_format).collect()),
+        None => Ok(parse_format(&json).into_iter().collect()),
+    }
+}
+
+/// Collect subtitle and automatic caption languages from yt-dlp's JSON
+fn parse_subtitles(json: &serde_json::Value) -> Vec<SubtitleTrack> {
+    let mut tracks = Vec::new();
+
+    for (key, automatic) in [("subtitles", false), ("automatic_captions", true)] {
+        let Some(langs) = json.get(key).and_then(|s| s.as_object()) else {
+            continue;
+        };
+        for (lang, entries) in langs {
+            // yt-dlp lists live chat replays as a subtitle track
+            if lang == "live_chat" || tracks.iter().any(|t: &SubtitleTrack| t.lang == *lang) {
+                continue;
+            }
+            let entries = entries.as_array().cloned().unwrap_or_default();
+            tracks.push(SubtitleTrack {
+                lang: lang.clone(),
+                name: entries
+                    .iter()
+                    .find_map(|e| e.get("name").and_then(|n| n.as_str()))
+                    .map(|n| n.to_string()),
+                formats: entries
+                    .iter()
+                    .filter_map(|e| e.get("ext").and_then(|x| x.as_str()))
+                    .map(|x| x.to_string())
+                    .collect(),
+                automatic,
+            });
+        }
+    }
+
+    tracks.sort_by(|a, b| a.automatic.cmp(&b.automatic).then_with(|| a.lang.cmp(&b.lang)));
+    tracks
+}
+
+/// yt-dlp arguments for downloading (and optionally embedding) subtitles
+fn subtitle_args(options: &SubtitleOptions) -> Vec<String> {
+    if options.languages.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = vec![
+        "--write-subs".to_string(),
+        "--sub-langs".to_string(),
+        options.languages.join(","),
+        "--sub-format".to_string(),
+        format!("{}/best", options.format.as_str()),
+        "--convert-subs".to_string(),
+        options.format.as_str().to_string(),
+    ];
+    if options.include_automatic {
+        args.push("--write-auto-subs".to_string());
+    }
+    if options.embed {
+        args.push("--embed-subs".to_string());
+    }
+    args
+}
+
+/// yt-dlp arguments for recording a live stream
+fn live_args(options: &LiveOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if options.from_start {
+        args.push("--live-from-start".to_string());
+    }
+    if let Some(interval) = options.wait_for_video {
+        args.push("--wait-for-video".to_string());
+        args.push(interval.max(1).to_string());
+    }
+    args
+}
+
+/// yt-dlp arguments for chapters, sections and SponsorBlock
+fn chapter_args(options: &ChapterOptions, output_template: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    if options.split {
+        let dir = Path::new(output_template).parent().unwrap_or(Path::new(""));
+        args.push("--split-chapters".to_string());
+        args.push("-o".to_string());
+        args.push(format!("chapter:{}", dir.join(CHAPTER_TEMPLATE).to_string_lossy()));
+    }
+    for range in &options.sections {
+        args.push("--download-sections".to_string());
+        args.push(format!("*{}-{}", range.start, range.end));
+    }
+    if !options.sponsorblock_mark.is_empty() {
+        args.push("--sponsorblock-mark".to_string());
+        args.push(options.sponsorblock_mark.join(","));
+    }
+    if !options.sponsorblock_remove.is_empty() {
+        args.push("--sponsorblock-remove".to_string());
+        args.push(options.sponsorblock_remove.join(","));
+    }
+    args
+}
+
+/// yt-dlp arguments for embedding metadata and writing the info JSON
+fn embed_args(options: &EmbedOptions) -> Vec<String> {
+    let flags = [
+        (options.metadata, "--embed-metadata"),
+        (options.thumbnail, "--embed-thumbnail"),
+        (options.chapters, "--embed-chapters"),
+        (options.info_json, "--write-info-json"),
+    ];
+    flags
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, flag)| flag.to_string())
+        .collect()
+}
+
+/// yt-dlp arguments for audio extraction
+fn audio_args(options: &AudioOptions) -> Vec<String> {
+    let mut args = vec![
+        "-x".to_string(),
+        "--audio-format".to_string(),
+        options.format.as_str().to_string(),
+    ];
+    if let Some(quality) = options.quality.as_ref().filter(|q| !q.trim().is_empty()) {
+        args.push("--audio-quality".to_string());
+        args.push(quality.trim().to_string());
+    }
+    if options.embed_thumbnail {
+        args.push("--embed-thumbnail".to_string());
+    }
+    if options.embed_metadata {
+        args.push("--embed-metadata".to_string());
+    }
+    args
+}
+
+/// Subtitle files yt-dlp wrote next to the video, named `<stem>.<lang>.<ext>`
+fn find_subtitle_files(video_path: &str, options: &SubtitleOptions) -> Vec<String> {
+    let path = PathBuf::from(video_path);
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy();
+
+    let mut files = Vec::new();
+    for lang in &options.languages {
+        for ext in [options.format.as_str(), "vtt", "srt"] {
+            let candidate = dir.join(format!("{}.{}.{}", stem, lang, ext));
+            if candidate.exists() {
+                files.push(candidate.to_string_lossy().to_string());
+                break;
+            }
+        }
+    }
+    files
+}
+
+/// List the entries of a playlist or channel without resolving each video
+pub async fn fetch_playlist_info(ytdlp: &[String], url: &str, extra_args: &[String]) -> Result<PlaylistInfo, String> {
+    if !is_ytdlp_installed(ytdlp) {
+        return Err("yt-dlp not installed".to_string());
+    }
+
+    let output = ytdlp_command(ytdlp)
+        .args([
+            "--flat-playlist",
+            "--dump-single-json",
+            "--no-warnings",
+            "--yes-playlist",
+        ])
+        .args(extra_args)
+        .arg(url)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run yt-dlp: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+    let get_str = |v: &serde_json::Value, key: &str| {
+        v.get(key).and_then(|s| s.as_str()).map(|s| s.to_string())
+    };
+
+    let entries: Vec<PlaylistEntry> = json
+        .get("entries")
+        .and_then(|e| e.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .enumerate()
+                .filter_map(|(i, e)| {
+                    let id = get_str(e, "id")?;
+                    // Flat entries usually carry the video URL; fall back to the page URL
+                    let url = get_str(e, "url")
+                        .filter(|u| u.starts_with("http"))
+                        .or_else(|| get_str(e, "webpage_url"))?;
+                    Some(PlaylistEntry {
+                        index: e
+                            .get("playlist_index")
+                            .and_then(|n| n.as_u64())
+                            .unwrap_or(i as u64 + 1),
+                        title: get_str(e, "title").unwrap_or_else(|| id.clone()),
+                        id,
+                        url,
+                        duration: e.get("duration").and_then(|d| d.as_f64()),
+                        uploader: get_str(e, "uploader").or_else(|| get_str(e, "channel")),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if entries.is_empty() {
+        return Err("No playlist entries found".to_string());
+    }
+
+    Ok(PlaylistInfo {
+        url: url.to_string(),
+        id: get_str(&json, "id"),
+        title: get_str(&json, "title").unwrap_or_else(|| "Playlist".to_string()),
+        uploader: get_str(&json, "uploader").or_else(|| get_str(&json, "channel")),
+        entries,
     })
 }
 
@@ -260,57 +990,106 @@ fn extract_height(resolution: &Option<String>) -> u32 {
 
 /// Download video using yt-dlp
 pub async fn download_video(
-    app: AppHandle,
+    ctx: &DownloadContext,
+    settings: &Settings,
     id: String,
     url: String,
-    format_id: String,
-    output_dir: String,
+    options: VideoOptions,
     handle: Arc<VideoDownloadHandle>,
-    concurrent_fragments: u32,
-    speed_limit: u64,
 ) -> Result<String, String> {
-    let ytdlp_path = get_ytdlp_path();
 
-    if !ytdlp_path.exists() {
+    if !is_ytdlp_installed(&settings.ytdlp_command) {
         return Err("yt-dlp not installed".to_string());
     }
 
-    // Build output template
-    let output_template = format!("{}/%(title)s.%(ext)s", output_dir);
-
     // Build yt-dlp command
-    let mut cmd = Command::new(&ytdlp_path);
+    let mut cmd = ytdlp_command(&settings.ytdlp_command);
+
+    // yt-dlp appends the final path and metadata here once the file is in place
+    let result_file = std::env::temp_dir().join(format!("wdm-{}.jsonl", id));
+    let _ = tokio::fs::remove_file(&result_file).await;
 
     let mut args = vec![
         "--newline".to_string(),
         "--progress".to_string(),
         "--progress-template".to_string(),
-        "download:WDM:%(progress._percent_str)s|%(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|%(progress.speed)s|%(progress.eta)s".to_string(),
+        "download:WDM:%(progress)j".to_string(),
+        "--progress-template".to_string(),
+        "postprocess:WDM_PP:%(progress)j".to_string(),
+        "--print-to-file".to_string(),
+        "after_move:%(.{filepath,id,title,uploader,duration,ext,chapters})j".to_string(),
+        result_file.to_string_lossy().to_string(),
         "-f".to_string(),
-        format_id,
+        options.format_id.clone(),
         "-o".to_string(),
-        output_template,
+        options.output_template.clone(),
         "--no-playlist".to_string(),
+        // Pick up .part files left by a paused or interrupted run
+        "--continue".to_string(),
     ];
 
-    // Add concurrent fragment downloads (for HLS/DASH streams)
+    if !options.format_sort.is_empty() {
+        args.push("-S".to_string());
+        args.push(options.format_sort.join(","));
+    }
+
+    if let Some(container) = &options.merge_output_format {
+        args.push("--merge-output-format".to_string());
+        args.push(container.clone());
+    }
+
+    if let Some(subtitles) = &options.subtitles {
+        args.extend(subtitle_args(subtitles));
+    }
+
+    if let Some(live) = &options.live {
+        args.extend(live_args(live));
+    }
+
+    if let Some(audio) = &options.audio {
+        args.extend(audio_args(audio));
+    }
+
+    if let Some(chapters) = &options.chapters {
+        args.extend(chapter_args(chapters, &options.output_template));
+    }
+
+    if let Some(embed) = &options.embed {
+        args.extend(embed_args(embed));
+    }
+
+    // Point yt-dlp at the ffmpeg we resolved (custom, managed or PATH)
+    if let Some((ffmpeg, _)) = find_ffmpeg(settings.ffmpeg_path.as_deref()) {
+        args.push("--ffmpeg-location".to_string());
+        args.push(ffmpeg.to_string_lossy().to_string());
+    }
+
+    // Add concurrent fragment downloads (for HLS/DASH streams), capped at 16 for yt-dlp
+    let concurrent_fragments = (settings.connections as u32).min(16);
     if concurrent_fragments > 1 {
         args.push("--concurrent-fragments".to_string());
         args.push(concurrent_fragments.to_string());
     }
 
     // Add speed limit if set
-    if speed_limit > 0 {
+    if settings.speed_limit > 0 {
         args.push("--limit-rate".to_string());
-        args.push(format!("{}K", speed_limit / 1024)); // Convert to KB/s
+        args.push(format!("{}K", settings.speed_limit / 1024)); // Convert to KB/s
     }
 
-    args.push(url);
+    // Cookies and user arguments go last so they can override ours
+    args.extend(settings.ytdlp_args(&url));
+
+    args.push(url.clone());
 
     cmd.args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
+    // A hidden console of its own lets a recording be stopped with Ctrl+C
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to spawn yt-dlp: {}", e))?;
@@ -318,13 +1097,23 @@ pub async fn download_video(
     let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
 
+    // Keep the process on the handle so pause and cancel can kill it at any time
+    *handle.process.lock().await = Some(child);
+
+    let log = Arc::new(Mutex::new(DownloadLog::open(&id).await));
+    log.lock().await.write_line("wdm", &format!("yt-dlp {}", args.join(" "))).await;
+
+    // Drain stderr while the process runs so a full pipe can't block yt-dlp
+    let stderr_task = tokio::spawn(read_stderr(Arc::clone(&ctx.events), id.clone(), stderr, log.clone()));
+
     let reader = BufReader::new(stdout);
     let mut lines = reader.lines();
 
-    let mut final_filename = String::new();
+    // File yt-dlp was last writing to, in case it never reports a final path
+    let mut last_progress_file = String::new();
 
     // Emit initial progress
-    app.emit("download-progress", serde_json::json!({
+    ctx.events.event("download-progress", serde_json::json!({
         "id": id,
         "downloaded": 0,
         "total": 0,
@@ -332,255 +1121,354 @@ pub async fn download_video(
         "status": "starting",
         "chunk_progress": [],
         "eta": null
-    })).ok();
+    }));
 
     // Update history status to downloading
     {
-        let state = app.state::<AppState>();
-        let mut history = state.history.write().await;
+        let mut history = ctx.history.write().await;
         history.update_download(&id, |r| {
             r.status = crate::persistence::DownloadStatus::Downloading;
         });
-        let _ = history.save().await;
     }
 
     let mut last_history_update = std::time::Instant::now();
+    let is_live = options.live.is_some();
 
     // Process output lines
     while let Ok(Some(line)) = lines.next_line().await {
-        // Check for cancellation
-        if handle.cancelled.load(Ordering::Relaxed) {
-            child.kill().await.ok();
-            
-            // Update history
-            let state = app.state::<AppState>();
-            let mut history = state.history.write().await;
-            history.update_download(&id, |r| {
-                r.status = crate::persistence::DownloadStatus::Cancelled;
-            });
-            let _ = history.save().await;
-
-            return Err("Download cancelled".to_string());
+        // Progress lines are shown live and would flood the log
+        if !line.starts_with("WDM:") {
+            log.lock().await.write_line("stdout", &line).await;
         }
 
+        // Scheduled streams wait before anything downloads
+        if line.starts_with("[wait]") {
+            ctx.events.event("download-progress", serde_json::json!({
+                "id": id,
+                "downloaded": 0,
+                "total": 0,
+                "speed": 0.0,
+                "status": "waiting",
+                "chunk_progress": [],
+                "eta": null
+            }));
+        }
 
+        // Check for pause or cancellation
+        if handle.cancelled.load(Ordering::Relaxed) || handle.paused.load(Ordering::Relaxed) {
+            handle.kill().await;
+            break;
+        }
 
         // Parse progress line (custom template format)
         // The output will start with "WDM:" because "download:" is the type selector
         if line.starts_with("WDM:") {
             if let Some(progress) = parse_progress_line(&line, &id) {
-                app.emit("download-progress", serde_json::json!({
-                    "id": progress.id,
-                    "downloaded": progress.downloaded_bytes,
-                    "total": progress.total_bytes,
-                    "speed": progress.speed,
-                    "status": "downloading",
-                    "chunk_progress": [],
-                    "eta": progress.eta,
-                    "percent": progress.percent
-                })).ok();
+                if !progress.filename.is_empty() && progress.filename != last_progress_file {
+                    last_progress_file = progress.filename.clone();
+                    // Show a real name in history while the download runs
+                    let name = PathBuf::from(last_progress_file.trim_end_matches(".part"))
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    let mut history = ctx.history.write().await;
+                    history.update_download(&id, |r| r.filename = name);
+                }
+                if is_live {
+                    // A live stream has no known end, so report time and bytes recorded
+                    ctx.events.event("download-progress", serde_json::json!({
+                        "id": progress.id,
+                        "downloaded": progress.downloaded_bytes,
+                        "total": 0,
+                        "speed": progress.speed,
+                        "status": "recording",
+                        "chunk_progress": [],
+                        "eta": null,
+                        "elapsed": progress.elapsed
+                    }));
+                } else {
+                    ctx.events.event("download-progress", serde_json::json!({
+                        "id": progress.id,
+                        "downloaded": progress.downloaded_bytes,
+                        "total": progress.total_bytes,
+                        "speed": progress.speed,
+                        "status": "downloading",
+                        "chunk_progress": [],
+                        "eta": progress.eta,
+                        "percent": progress.percent
+                    }));
+                }
 
                 // Update history periodically (every 1 second)
                 if last_history_update.elapsed().as_secs() >= 1 {
-                    let state = app.state::<AppState>();
-                    let mut history = state.history.write().await;
+                    let mut history = ctx.history.write().await;
                     history.update_video_progress(&id, progress.downloaded_bytes, progress.total_bytes);
-                    let _ = history.save().await;
                     last_history_update = std::time::Instant::now();
                 }
             }
         }
 
-        // Check for destination line
-        if line.contains("Destination:") {
-            if let Some(part) = line.split("Destination:").nth(1) {
-                final_filename = part.trim().to_string();
-                // Update filename in history immediately if found
-                let state = app.state::<AppState>();
-                let mut history = state.history.write().await;
-                history.update_download(&id, |r| {
-                    r.filename = PathBuf::from(&final_filename)
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-                    r.file_path = final_filename.clone();
-                });
-            }
-        }
-        
-        // Check for "Merging formats into"
-        if line.contains("Merging formats into") {
-            if let Some(part) = line.split("into").nth(1) {
-                 // Clean up quotes if present
-                 let fname = part.trim().trim_matches('"').to_string();
-                 if !fname.is_empty() {
-                     final_filename = fname;
-                     // Update filename in history
-                    let state = app.state::<AppState>();
-                    let mut history = state.history.write().await;
-                    history.update_download(&id, |r| {
-                        r.filename = PathBuf::from(&final_filename)
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-                        r.file_path = final_filename.clone();
-                    });
-                 }
+        // Post-processing progress; merging is the step that takes a while
+        if let Some(json) = line.strip_prefix("WDM_PP:") {
+            let progress: serde_json::Value = serde_json::from_str(json).unwrap_or_default();
+            let started = progress.get("status").and_then(|s| s.as_str()) == Some("started");
+            let merging = progress.get("postprocessor").and_then(|p| p.as_str()) == Some("Merger");
+            if started && merging {
+                ctx.events.event("download-progress", serde_json::json!({
+                    "id": id,
+                    "downloaded": 0,
+                    "total": 0,
+                    "speed": 0.0,
+                    "status": "merging",
+                    "chunk_progress": [],
+                    "eta": null
+                }));
             }
         }
+    }
 
-        // Check for already downloaded
-        if line.contains("has already been downloaded") {
-            // Extract filename from the message
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            for part in parts.iter() {
-                if part.ends_with(".mp4")
-                    || part.ends_with(".webm")
-                    || part.ends_with(".mkv")
-                    || part.ends_with(".m4a")
-                {
-                    final_filename = part.to_string();
-                    break;
-                }
-            }
-        }
+    // Wait for process to complete
+    let child = handle.process.lock().await.take();
+    let status = match child {
+        Some(mut child) => child
+            .wait()
+            .await
+            .map_err(|e| format!("Failed to wait for yt-dlp: {}", e))?,
+        None => return Err("yt-dlp process is missing".to_string()),
+    };
 
-        // Check for merge message - emit merging status
-        if line.contains("[Merger]") || line.contains("Merging formats") {
-            app.emit("download-progress", serde_json::json!({
-                "id": id,
-                "downloaded": 0,
-                "total": 0,
-                "speed": 0.0,
-                "status": "merging",
-                "chunk_progress": [],
-                "eta": null
-            })).ok();
-        }
+    let error_msg = stderr_task.await.unwrap_or_default();
+    {
+        let mut log = log.lock().await;
+        log.write_line("wdm", &format!("yt-dlp exited with {}", status)).await;
+        log.flush().await;
     }
 
-    // Wait for process to complete
-    let status = child
-        .wait()
-        .await
-        .map_err(|e| format!("Failed to wait for yt-dlp: {}", e))?;
-
-    if !status.success() {
-        // Read stderr for error message
-        let stderr_reader = BufReader::new(stderr);
-        let mut stderr_lines = stderr_reader.lines();
-        let mut error_msg = String::new();
-        while let Ok(Some(line)) = stderr_lines.next_line().await {
-            error_msg.push_str(&line);
-            error_msg.push('\n');
-        }
+    if handle.cancelled.load(Ordering::Relaxed) {
+        let mut history = ctx.history.write().await;
+        history.update_download(&id, |r| {
+            r.status = crate::persistence::DownloadStatus::Cancelled;
+        });
+
+        return Err("Download cancelled".to_string());
+    }
+
+    if handle.paused.load(Ordering::Relaxed) {
+        let mut history = ctx.history.write().await;
+        history.update_download(&id, |r| {
+            r.status = crate::persistence::DownloadStatus::Paused;
+        });
 
+        ctx.events.event("download-progress", serde_json::json!({
+            "id": id,
+            "downloaded": 0,
+            "total": 0,
+            "speed": 0.0,
+            "status": "paused",
+            "chunk_progress": [],
+            "eta": null
+        }));
+
+        return Err("Download paused".to_string());
+    }
+
+    // A stopped recording exits like an interrupted run, but its file is what we want
+    if !status.success() && !handle.stopping.load(Ordering::Relaxed) {
         // Update history to failed
-        let state = app.state::<AppState>();
-        let mut history = state.history.write().await;
+        let mut history = ctx.history.write().await;
         history.update_download(&id, |r| {
             r.status = crate::persistence::DownloadStatus::Failed;
         });
-        let _ = history.save().await;
 
         return Err(format!("yt-dlp failed: {}", error_msg));
     }
 
+    let (final_filename, metadata, chapter_files) = match read_result_file(&result_file).await {
+        Some(result) => {
+            let chapter_files: Vec<String> = result
+                .chapters
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|c| c.filepath)
+                .filter(|p| Path::new(p).is_file())
+                .collect();
+            (result.filepath, Some(result.metadata), chapter_files)
+        }
+        // An interrupted recording may stop before yt-dlp reports the file
+        None => (last_progress_file.trim_end_matches(".part").to_string(), None, Vec::new()),
+    };
+    let _ = tokio::fs::remove_file(&result_file).await;
+
+    // The info JSON knows far more about the video than the printed fields
+    let wants_info_json = options.embed.as_ref().is_some_and(|e| e.info_json);
+    let metadata = match metadata {
+        Some(_) if wants_info_json => read_info_json(&final_filename).await.or(metadata),
+        _ => metadata,
+    };
+
+    let total_size = match tokio::fs::metadata(&final_filename).await {
+        Ok(meta) if !final_filename.is_empty() => meta.len(),
+        _ => 0,
+    };
+
+    let subtitle_files = match &options.subtitles {
+        Some(subtitles) if !final_filename.is_empty() => find_subtitle_files(&final_filename, subtitles),
+        _ => Vec::new(),
+    };
+
+    let filename = PathBuf::from(&final_filename)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    // Sizes are read before taking the history lock
+    let mut chapter_sizes = Vec::with_capacity(chapter_files.len());
+    for path in chapter_files {
+        let size = tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+        chapter_sizes.push((path, size));
+    }
+
     // Update history to completed
     {
-        let state = app.state::<AppState>();
-        let mut history = state.history.write().await;
+        let mut history = ctx.history.write().await;
+        history.update_video_progress(&id, total_size, total_size);
         history.update_download(&id, |r| {
             r.status = crate::persistence::DownloadStatus::Completed;
+            r.subtitle_files = subtitle_files;
+            if metadata.is_some() {
+                r.video_metadata = metadata;
+            }
             // Ensure path is updated if we have it
             if !final_filename.is_empty() {
-                 r.filename = PathBuf::from(&final_filename)
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-                 r.file_path = final_filename.clone();
+                r.filename = filename.clone();
+                r.file_path = final_filename.clone();
             }
         });
-        let _ = history.save().await;
+
+        add_chapter_records(&mut history, &id, &url, &chapter_sizes);
     }
 
     // Emit completion
-    app.emit(
+    ctx.events.event(
         "download-complete",
         serde_json::json!({
             "id": id,
             "path": final_filename,
-            "filename": PathBuf::from(&final_filename).file_name().unwrap_or_default().to_string_lossy(),
-            "total_size": 0 // We might not know the final size easily, or could stat the file
+            "filename": filename,
+            "total_size": total_size
         }),
-    )
-    .ok();
+    );
 
     Ok(final_filename)
 }
 
-/// Parse yt-dlp progress line
-fn parse_progress_line(line: &str, id: &str) -> Option<VideoProgress> {
-    // Format: WDM:PERCENT_STR|DOWNLOADED|TOTAL|TOTAL_ESTIMATE|SPEED|ETA
-    let content = line.strip_prefix("WDM:")?;
-    let parts: Vec<&str> = content.split('|').collect();
+/// Track split chapter files as children of the video, replacing those of an earlier run
+fn add_chapter_records(history: &mut DownloadHistory, id: &str, url: &str, files: &[(String, u64)]) {
+    let prefix = format!("{}_chapter_", id);
+    let stale: Vec<String> = history
+        .downloads
+        .keys()
+        .filter(|k| k.starts_with(&prefix))
+        .cloned()
+        .collect();
+    for child in stale {
+        history.remove_download(&child);
+    }
 
-    if parts.len() < 6 {
-        return None;
+    for (i, (path, size)) in files.iter().enumerate() {
+        let size = *size;
+        let name = Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let child_id = format!("{}{}", prefix, i + 1);
+        let mut record = DownloadRecord::new(
+            child_id.clone(),
+            url.to_string(),
+            name,
+            path.clone(),
+            size,
+            false,
+            1,
+            true,
+            None,
+        );
+        record.parent_id = Some(id.to_string());
+        record.status = DownloadStatus::Completed;
+        history.add_download(record);
+        history.update_video_progress(&child_id, size, size);
     }
+}
 
-    // Helper to parse numeric strings that might be floats or "NA"
-    let parse_num = |s: &str| -> f64 {
-        if s.contains("NA") { 0.0 } else { s.trim().parse().unwrap_or(0.0) }
-    };
+/// Log yt-dlp's stderr, forward warnings to the frontend and return the error text
+async fn read_stderr(
+    events: Arc<dyn EventSink>,
+    id: String,
+    stderr: tokio::process::ChildStderr,
+    log: Arc<Mutex<DownloadLog>>,
+) -> String {
+    let mut lines = BufReader::new(stderr).lines();
+    let mut errors = Vec::new();
+    let mut tail: Vec<String> = Vec::new();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        log.lock().await.write_line("stderr", &line).await;
 
-    // Try to parse percent from string (e.g. " 10.5%")
-    let percent_str = parts[0].trim().trim_end_matches('%');
-    let mut percent: f64 = parse_num(percent_str);
-
-    // Parse bytes as f64 first to handle "1234.0", then cast to u64
-    let downloaded: u64 = parse_num(parts[1]) as u64;
-    
-    let total_exact: u64 = parse_num(parts[2]) as u64;
-    let total_est: u64 = parse_num(parts[3]) as u64;
-    let mut total = if total_exact > 0 { total_exact } else { total_est };
-
-    let speed: f64 = parse_num(parts[4]);
-    let eta_str = parts[5].trim();
-    let eta: Option<u64> = if eta_str.contains("NA") { None } else { eta_str.parse().ok() };
-
-    // Strategy for Total Size:
-    // 1. If we have a valid percentage (> 0) and downloaded bytes, we can infer the total size.
-    //    This makes the progress bar consistent (e.g. if 10MB is 50%, Total MUST be 20MB).
-    // 2. This is often more reliable than yt-dlp's total estimate which can be off or "NA".
-    if percent > 0.01 && downloaded > 0 {
-        let inferred_total = (downloaded as f64 / (percent / 100.0)) as u64;
-        
-        // Use inferred total if:
-        // - Reported total is 0 (unknown)
-        // - Reported total is suspiciously small (< 50KB) (likely manifest size)
-        // - Reported total is less than downloaded (impossible)
-        if total == 0 || total < 50 * 1024 || total < downloaded {
-            total = inferred_total;
+        if let Some(message) = line.strip_prefix("WARNING: ") {
+            events.event("video-warning", serde_json::json!({
+                "id": id,
+                "message": message
+            }));
+        } else if line.starts_with("ERROR: ") {
+            errors.push(line.clone());
+        }
+
+        // Tracebacks and other unprefixed failures only show up in the last lines
+        tail.push(line);
+        if tail.len() > STDERR_TAIL_LINES {
+            tail.remove(0);
+        }
+    }
+
+    if errors.is_empty() { tail } else { errors }.join("\n")
+}
+
+/// Parse yt-dlp progress line
+fn parse_progress_line(line: &str, id: &str) -> Option<VideoProgress> {
+    // Format: WDM:<progress hook dict as JSON>
+    let raw: RawProgress = serde_json::from_str(line.strip_prefix("WDM:")?).ok()?;
+
+    let downloaded = raw.downloaded_bytes.unwrap_or(0.0) as u64;
+    let mut total = raw.total_bytes.or(raw.total_bytes_estimate).unwrap_or(0.0) as u64;
+    let mut percent = 0.0;
+
+    if total > 0 {
+        percent = downloaded as f64 / total as f64 * 100.0;
+    } else if let (Some(index), Some(count)) = (raw.fragment_index, raw.fragment_count) {
+        // Fragmented streams often have no size; infer one from fragment progress
+        if count > 0.0 && index > 0.0 {
+            percent = index / count * 100.0;
+            total = (downloaded as f64 / (index / count)) as u64;
         }
     }
 
-    // Fallback: If percent is 0 but we have totals, calculate percent
-    if percent == 0.0 && total > 0 {
-        percent = (downloaded as f64 / total as f64) * 100.0;
+    // Estimates can undershoot what has already arrived
+    if total < downloaded {
+        total = downloaded;
     }
 
     Some(VideoProgress {
         id: id.to_string(),
-        status: "downloading".to_string(),
-        percent,
+        status: raw.status.unwrap_or_else(|| "downloading".to_string()),
+        percent: percent.min(100.0),
         downloaded_bytes: downloaded,
         total_bytes: total,
-        speed,
-        eta,
-        filename: String::new(),
+        speed: raw.speed.unwrap_or(0.0),
+        eta: raw.eta.map(|e| e as u64),
+        elapsed: raw.elapsed,
+        filename: raw.filename.unwrap_or_default(),
     })
 }
@@ -1,16 +1,20 @@
+use regex::Regex;
 use reqwest::Client;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 #[cfg(target_os = "macos")]
-const YTDLP_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_macos";
+const YTDLP_ASSET: &str = "yt-dlp_macos";
 
 #[cfg(target_os = "windows")]
-const YTDLP_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp.exe";
+const YTDLP_ASSET: &str = "yt-dlp.exe";
 
 #[cfg(target_os = "linux")]
-const YTDLP_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_linux";
+const YTDLP_ASSET: &str = "yt-dlp_linux";
 
 #[cfg(target_os = "macos")]
 const YTDLP_BINARY_NAME: &str = "yt-dlp";
@@ -21,6 +25,62 @@ const YTDLP_BINARY_NAME: &str = "yt-dlp.exe";
 #[cfg(target_os = "linux")]
 const YTDLP_BINARY_NAME: &str = "yt-dlp";
 
+/// Checksum file published with every yt-dlp release
+const CHECKSUMS_ASSET: &str = "SHA2-256SUMS";
+
+/// Release tags: "2024.08.06" on stable, "2024.08.06.232908" on nightly
+static VERSION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d{4}\.\d{2}\.\d{2}(\.\d+)?$").unwrap());
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum YtdlpChannel {
+    #[default]
+    Stable,
+    Nightly,
+}
+
+impl YtdlpChannel {
+    fn repo_url(&self) -> &'static str {
+        match self {
+            YtdlpChannel::Stable => "https://github.com/yt-dlp/yt-dlp",
+            YtdlpChannel::Nightly => "https://github.com/yt-dlp/yt-dlp-nightly-builds",
+        }
+    }
+}
+
+/// Which yt-dlp build to install: the latest on a channel, or a pinned version
+#[derive(Debug, Clone, Default)]
+pub struct YtdlpRelease {
+    pub channel: YtdlpChannel,
+    pub version: Option<String>,
+}
+
+/// Check that `version` looks like a yt-dlp release tag before it ends up in a download URL
+pub fn validate_version(version: &str) -> Result<(), String> {
+    if VERSION_REGEX.is_match(version) {
+        Ok(())
+    } else {
+        Err(format!("Invalid yt-dlp version: {} (expected YYYY.MM.DD)", version))
+    }
+}
+
+impl YtdlpRelease {
+    fn asset_url(&self, asset: &str) -> String {
+        match &self.version {
+            Some(version) => format!("{}/releases/download/{}/{}", self.channel.repo_url(), version, asset),
+            None => format!("{}/releases/latest/download/{}", self.channel.repo_url(), asset),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct YtdlpUpdate {
+    pub previous: Option<String>,
+    pub current: String,
+    pub updated: bool,
+}
+
 /// Get the directory where yt-dlp binary is stored
 pub fn get_ytdlp_dir() -> PathBuf {
     dirs::data_dir()
@@ -29,37 +89,74 @@ pub fn get_ytdlp_dir() -> PathBuf {
         .join("bin")
 }
 
-/// Get the full path to the yt-dlp binary
-pub fn get_ytdlp_path() -> PathBuf {
+/// Get the full path to the yt-dlp binary WDM installs and updates
+pub fn get_managed_ytdlp_path() -> PathBuf {
     get_ytdlp_dir().join(YTDLP_BINARY_NAME)
 }
 
-/// Check if yt-dlp is installed in our app directory
-pub fn is_ytdlp_installed() -> bool {
-    get_ytdlp_path().exists()
+/// Program and leading arguments to run yt-dlp with: the custom command from
+/// Settings (e.g. `python3 -m yt_dlp`) if set, else the managed binary
+pub fn ytdlp_invocation(custom_command: &[String]) -> (PathBuf, &[String]) {
+    match custom_command.split_first() {
+        Some((program, args)) if !program.trim().is_empty() => (PathBuf::from(program), args),
+        _ => (get_managed_ytdlp_path(), &[]),
+    }
+}
+
+/// A command that runs yt-dlp, ready for our arguments
+pub fn ytdlp_command(custom_command: &[String]) -> tokio::process::Command {
+    let (program, args) = ytdlp_invocation(custom_command);
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args);
+    cmd
+}
+
+/// A bare program name like `python3` is looked up on PATH
+fn find_program(program: &Path) -> bool {
+    if program.components().count() > 1 {
+        return program.is_file();
+    }
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    let exe = format!("{}{}", program.display(), std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&path).any(|dir| dir.join(program).is_file() || dir.join(&exe).is_file())
+}
+
+/// Check if yt-dlp can be run: the custom command's program, or our managed binary
+pub fn is_ytdlp_installed(custom_command: &[String]) -> bool {
+    let (program, _) = ytdlp_invocation(custom_command);
+    find_program(&program)
 }
 
-/// Download yt-dlp binary from GitHub releases
-pub async fn download_ytdlp<F>(progress_callback: F) -> Result<PathBuf, String>
+fn http_client() -> Result<Client, String> {
+    Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Download yt-dlp binary from GitHub releases, verify it against the
+/// release checksums and swap it in, rolling back if it doesn't run
+pub async fn download_ytdlp<F>(release: &YtdlpRelease, progress_callback: F) -> Result<PathBuf, String>
 where
     F: Fn(u64, u64) + Send + 'static,
 {
     let ytdlp_dir = get_ytdlp_dir();
-    let ytdlp_path = get_ytdlp_path();
+    let ytdlp_path = get_managed_ytdlp_path();
 
     // Create bin directory if it doesn't exist
     fs::create_dir_all(&ytdlp_dir)
         .await
         .map_err(|e| format!("Failed to create bin directory: {}", e))?;
 
-    // Download the binary
-    let client = Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = http_client()?;
+    let expected_hash =
+        fetch_checksum(&client, &release.asset_url(CHECKSUMS_ASSET), YTDLP_ASSET).await?;
 
+    // Download the binary
     let response = client
-        .get(YTDLP_URL)
+        .get(release.asset_url(YTDLP_ASSET))
         .send()
         .await
         .map_err(|e| format!("Failed to download yt-dlp: {}", e))?;
@@ -73,6 +170,7 @@ where
 
     let total_size = response.content_length().unwrap_or(0);
     let mut downloaded: u64 = 0;
+    let mut hasher = Sha256::new();
 
     // Create temporary file
     let temp_path = ytdlp_path.with_extension("tmp");
@@ -89,6 +187,7 @@ where
         file.write_all(&chunk)
             .await
             .map_err(|e| format!("Failed to write file: {}", e))?;
+        hasher.update(&chunk);
 
         downloaded += chunk.len() as u64;
         progress_callback(downloaded, total_size);
@@ -99,6 +198,20 @@ where
         .map_err(|e| format!("Failed to flush file: {}", e))?;
     drop(file);
 
+    if let Err(e) = verify_sha256(hasher, &expected_hash, "yt-dlp") {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e);
+    }
+
+    // Keep the current binary until the new one proves it runs
+    let backup_path = ytdlp_path.with_extension("old");
+    let had_previous = ytdlp_path.exists();
+    if had_previous {
+        fs::rename(&ytdlp_path, &backup_path)
+            .await
+            .map_err(|e| format!("Failed to back up yt-dlp: {}", e))?;
+    }
+
     // Rename temp file to final path
     fs::rename(&temp_path, &ytdlp_path)
         .await
@@ -118,30 +231,160 @@ where
             .map_err(|e| format!("Failed to set executable permission: {}", e))?;
     }
 
+    if let Err(e) = binary_version(&ytdlp_path).await {
+        if had_previous {
+            fs::rename(&backup_path, &ytdlp_path)
+                .await
+                .map_err(|e| format!("Failed to restore previous yt-dlp: {}", e))?;
+        } else {
+            let _ = fs::remove_file(&ytdlp_path).await;
+        }
+        return Err(format!("New yt-dlp failed to run, kept the previous version: {}", e));
+    }
+
+    if had_previous {
+        let _ = fs::remove_file(&backup_path).await;
+    }
+
     Ok(ytdlp_path)
 }
 
-/// Ensure yt-dlp is installed, download if needed
-pub async fn ensure_ytdlp<F>(progress_callback: F) -> Result<PathBuf, String>
+/// Look up the expected SHA-256 of `asset` in a published checksum list
+pub async fn fetch_checksum(client: &Client, sums_url: &str, asset: &str) -> Result<String, String> {
+    let response = client
+        .get(sums_url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download checksums for {}: {}", asset, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to download checksums for {}: HTTP {}",
+            asset,
+            response.status()
+        ));
+    }
+
+    let sums = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read checksums for {}: {}", asset, e))?;
+
+    // Lines look like "<sha256>  <asset name>"
+    sums.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == asset)
+        .map(|(hash, _)| hash.to_lowercase())
+        .ok_or_else(|| format!("No checksum published for {}", asset))
+}
+
+/// Compare what was hashed while downloading `what` with the published checksum
+pub fn verify_sha256(hasher: Sha256, expected: &str, what: &str) -> Result<(), String> {
+    let actual: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    if actual != expected {
+        return Err(format!(
+            "{} checksum mismatch: expected {}, got {}",
+            what, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Latest version on a release channel, read from where `releases/latest` redirects to
+pub async fn get_latest_ytdlp_version(channel: YtdlpChannel) -> Result<String, String> {
+    let response = http_client()?
+        .get(format!("{}/releases/latest", channel.repo_url()))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to check for yt-dlp updates: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to check for yt-dlp updates: HTTP {}",
+            response.status()
+        ));
+    }
+
+    // ".../releases/tag/2024.08.06"
+    response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|tag| !tag.is_empty() && *tag != "latest")
+        .map(|tag| tag.to_string())
+        .ok_or_else(|| "Failed to determine latest yt-dlp version".to_string())
+}
+
+/// Install the requested release unless it is already the installed version
+pub async fn update_ytdlp<F>(release: &YtdlpRelease, progress_callback: F) -> Result<YtdlpUpdate, String>
 where
     F: Fn(u64, u64) + Send + 'static,
 {
-    if is_ytdlp_installed() {
-        Ok(get_ytdlp_path())
+    let previous = binary_version(&get_managed_ytdlp_path()).await.ok();
+    let target = match &release.version {
+        Some(version) => version.clone(),
+        None => get_latest_ytdlp_version(release.channel).await?,
+    };
+
+    if previous.as_deref() == Some(target.as_str()) {
+        return Ok(YtdlpUpdate {
+            current: target,
+            previous,
+            updated: false,
+        });
+    }
+
+    // Install the exact version we resolved so the checksum and binary match
+    let pinned = YtdlpRelease {
+        channel: release.channel,
+        version: Some(target),
+    };
+    let path = download_ytdlp(&pinned, progress_callback).await?;
+    let current = binary_version(&path).await?;
+
+    Ok(YtdlpUpdate {
+        previous,
+        current,
+        updated: true,
+    })
+}
+
+/// Ensure yt-dlp is installed, downloading the managed binary if needed.
+/// A custom command is never replaced; it has to be installed by the user.
+pub async fn ensure_ytdlp<F>(
+    custom_command: &[String],
+    release: &YtdlpRelease,
+    progress_callback: F,
+) -> Result<PathBuf, String>
+where
+    F: Fn(u64, u64) + Send + 'static,
+{
+    let (program, _) = ytdlp_invocation(custom_command);
+    if is_ytdlp_installed(custom_command) {
+        Ok(program)
+    } else if !custom_command.is_empty() {
+        Err(format!("yt-dlp command not found: {}", program.display()))
     } else {
-        download_ytdlp(progress_callback).await
+        download_ytdlp(release, progress_callback).await
     }
 }
 
 /// Get yt-dlp version
-pub async fn get_ytdlp_version() -> Result<String, String> {
-    let ytdlp_path = get_ytdlp_path();
-
-    if !ytdlp_path.exists() {
+pub async fn get_ytdlp_version(custom_command: &[String]) -> Result<String, String> {
+    if !is_ytdlp_installed(custom_command) {
         return Err("yt-dlp not installed".to_string());
     }
 
-    let output = tokio::process::Command::new(&ytdlp_path)
+    command_version(ytdlp_command(custom_command)).await
+}
+
+/// Run `<binary> --version`
+pub async fn binary_version(ytdlp_path: &Path) -> Result<String, String> {
+    command_version(tokio::process::Command::new(ytdlp_path)).await
+}
+
+async fn command_version(mut cmd: tokio::process::Command) -> Result<String, String> {
+    let output = cmd
         .arg("--version")
         .output()
         .await
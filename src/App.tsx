@@ -11,7 +11,13 @@ import {
   DownloadProgress,
   DownloadComplete,
   DownloadError,
+  VideoWarning,
   VideoInfo,
+  VideoDownloadRequest,
+  RpcSettings,
+  PlaylistInfo,
+  PlaylistEntry,
+  FormatPreference,
 } from "./types";
 import { formatBytes } from "./utils";
 
@@ -22,6 +28,7 @@ import { RenamePrompt } from "./components/RenamePrompt";
 import { FileInfo } from "./components/FileInfo";
 import { AddDownloadForm } from "./components/AddDownloadForm";
 import { VideoInfoComponent } from "./components/VideoInfo";
+import { PlaylistSelect } from "./components/PlaylistSelect";
 
 function App() {
   const [url, setUrl] = useState("");
@@ -42,9 +49,11 @@ function App() {
   const [showHistory, setShowHistory] = useState(false);
   const [downloadFolder, setDownloadFolder] = useState("");
   const [speedLimit, setSpeedLimit] = useState(0);
+  const [rpcSettings, setRpcSettings] = useState<RpcSettings | null>(null);
 
   // Video download state
   const [videoInfo, setVideoInfo] = useState<VideoInfo | null>(null);
+  const [playlistInfo, setPlaylistInfo] = useState<PlaylistInfo | null>(null);
   const [ytdlpInstalling, setYtdlpInstalling] = useState(false);
   const [ytdlpProgress, setYtdlpProgress] = useState<{ downloaded: number; total: number } | null>(null);
 
@@ -52,6 +61,7 @@ function App() {
     invoke<number>("get_connections").then(setConnections);
     invoke<string>("get_download_folder").then(setDownloadFolder);
     invoke<number>("get_speed_limit").then(setSpeedLimit);
+    invoke<RpcSettings>("get_rpc_settings").then(setRpcSettings);
     loadHistory();
 
     const unlistenProgress = listen<DownloadProgress>("download-progress", (event) => {
@@ -75,6 +85,7 @@ function App() {
           updatedDownload.speed = progress.speed;
           updatedDownload.status = progress.status as any;
           if (progress.eta !== undefined) updatedDownload.eta = progress.eta;
+          if (progress.elapsed !== undefined) updatedDownload.elapsed = progress.elapsed;
 
           newMap.set(progress.id, updatedDownload);
         }
@@ -113,6 +124,42 @@ function App() {
       loadHistory();
     });
 
+    const unlistenWarning = listen<VideoWarning>("video-warning", (event) => {
+      const warning = event.payload;
+      setDownloads((prev) => {
+        const newMap = new Map(prev);
+        const download = newMap.get(warning.id);
+        if (download) {
+          newMap.set(warning.id, {
+            ...download,
+            warnings: [...(download.warnings || []), warning.message],
+          });
+        }
+        return newMap;
+      });
+    });
+
+    // Downloads added over JSON-RPC rather than from this window
+    const unlistenAdded = listen<DownloadInfo>("download-added", (event) => {
+      const info = event.payload;
+      setDownloads((prev) => {
+        const newMap = new Map(prev);
+        newMap.set(info.id, {
+          id: info.id,
+          filename: info.filename,
+          url: info.url,
+          totalSize: info.total_size,
+          downloaded: 0,
+          speed: 0,
+          progress: 0,
+          status: "downloading",
+          created_at: info.created_at * 1000,
+          type: 'file'
+        });
+        return newMap;
+      });
+    });
+
     const unlistenYtdlpProgress = listen<{ downloaded: number; total: number }>("ytdlp-download-progress", (event) => {
       setYtdlpProgress(event.payload);
     });
@@ -121,6 +168,8 @@ function App() {
       unlistenProgress.then((fn) => fn());
       unlistenComplete.then((fn) => fn());
       unlistenError.then((fn) => fn());
+      unlistenWarning.then((fn) => fn());
+      unlistenAdded.then((fn) => fn());
       unlistenYtdlpProgress.then((fn) => fn());
     };
   }, []);
@@ -140,10 +189,12 @@ function App() {
     setError(null);
     setUrlInfo(null);
     setVideoInfo(null);
+    setPlaylistInfo(null);
 
     try {
-      // Check if this is a video URL
-      const isVideo = await invoke<boolean>("check_video_url", { url });
+      // Playlists and channels are handled by yt-dlp too
+      const isPlaylist = await invoke<boolean>("check_playlist_url", { url });
+      const isVideo = isPlaylist || (await invoke<boolean>("check_video_url", { url }));
 
       if (isVideo) {
         // Ensure yt-dlp is installed
@@ -158,9 +209,14 @@ function App() {
           }
         }
 
-        // Fetch video info
-        const info = await invoke<VideoInfo>("get_video_info", { url });
-        setVideoInfo(info);
+        if (isPlaylist) {
+          const info = await invoke<PlaylistInfo>("get_playlist_info", { url });
+          setPlaylistInfo(info);
+        } else {
+          // Fetch video info
+          const info = await invoke<VideoInfo>("get_video_info", { url });
+          setVideoInfo(info);
+        }
       } else {
         // Regular file download
         const info = await invoke<UrlInfo>("fetch_url_info", { url });
@@ -247,7 +303,11 @@ function App() {
 
   async function pauseDownload(id: string) {
     try {
-      await invoke("pause_download", { id });
+      if (downloads.get(id)?.type === 'video') {
+        await invoke("pause_video_download", { id });
+      } else {
+        await invoke("pause_download", { id });
+      }
     } catch (e) {
       console.error("Failed to pause:", e);
     }
@@ -255,12 +315,25 @@ function App() {
 
   async function resumeDownload(id: string) {
     try {
-      await invoke("resume_download", { id });
+      if (downloads.get(id)?.type === 'video') {
+        await invoke("resume_video_download", { id });
+      } else {
+        await invoke("resume_download", { id });
+      }
     } catch (e) {
       console.error("Failed to resume:", e);
     }
   }
 
+  // End a live recording and keep what has been recorded so far
+  async function stopRecording(id: string) {
+    try {
+      await invoke("stop_video_recording", { id });
+    } catch (e) {
+      setError(String(e));
+    }
+  }
+
   async function cancelDownload(id: string) {
     const download = downloads.get(id);
     try {
@@ -303,7 +376,7 @@ function App() {
             progress: 0,
             status: "downloading",
             created_at: Date.now(),
-            type: 'file'
+            type: histItem.is_video ? 'video' : 'file'
           });
           return newMap;
         });
@@ -375,8 +448,18 @@ function App() {
     }
   }
 
+  async function updateRpcSettings(rpc: RpcSettings) {
+    try {
+      // The backend fills in a secret when the server is enabled without one
+      const saved = await invoke<RpcSettings>("set_rpc_settings", { rpc });
+      setRpcSettings(saved);
+    } catch (e) {
+      setError(`Failed to update JSON-RPC settings: ${e}`);
+    }
+  }
+
   // Video download functions
-  async function startVideoDownload(formatId: string) {
+  async function startVideoDownload(formatId: string, request: VideoDownloadRequest | null = null) {
     if (!videoInfo) return;
     setError(null);
 
@@ -384,6 +467,7 @@ function App() {
       const downloadId = await invoke<string>("start_video_download", {
         url: videoInfo.url,
         formatId,
+        request: { ...request, info: videoInfo },
       });
 
       setDownloads((prev) => {
@@ -412,6 +496,50 @@ function App() {
     }
   }
 
+  async function startPlaylistDownload(entries: PlaylistEntry[], formatPreference: FormatPreference) {
+    if (!playlistInfo) return;
+    setError(null);
+
+    try {
+      const playlistId = await invoke<string>("start_playlist_download", {
+        url: playlistInfo.url,
+        title: playlistInfo.title,
+        sourceId: playlistInfo.id,
+        entries,
+        formatId: "best",
+        formatPreference,
+      });
+
+      // Entries are queued by the backend; list them so their progress shows up
+      const hist = await invoke<DownloadInfo[]>("get_download_history");
+      setHistory(hist);
+      setDownloads((prev) => {
+        const newMap = new Map(prev);
+        for (const entry of hist.filter((h) => h.parent_id === playlistId)) {
+          newMap.set(entry.id, {
+            id: entry.id,
+            url: entry.url,
+            filename: entry.filename,
+            totalSize: 0,
+            downloaded: 0,
+            speed: 0,
+            progress: 0,
+            status: "waiting",
+            created_at: entry.created_at * 1000,
+            type: 'video',
+            videoTitle: entry.filename
+          });
+        }
+        return newMap;
+      });
+
+      setUrl("");
+      setPlaylistInfo(null);
+    } catch (e) {
+      setError(String(e));
+    }
+  }
+
   const activeDownloads = Array.from(downloads.values()).filter(
     (d) => d.status !== "completed" && d.status !== "error" && d.status !== "cancelled"
   );
@@ -485,6 +613,8 @@ function App() {
             selectDownloadFolder={selectDownloadFolder}
             resetDownloadFolder={resetDownloadFolder}
             updateSpeedLimit={updateSpeedLimit}
+            rpcSettings={rpcSettings}
+            updateRpcSettings={updateRpcSettings}
           />
         )}
 
@@ -576,6 +706,15 @@ function App() {
           />
         )}
 
+        {/* Playlist */}
+        {playlistInfo && (
+          <PlaylistSelect
+            info={playlistInfo}
+            onDownload={startPlaylistDownload}
+            onCancel={() => setPlaylistInfo(null)}
+          />
+        )}
+
         {/* Interrupted Downloads */}
         {interruptedDownloads.length > 0 && !showHistory && (
           <section className="space-y-3">
@@ -627,6 +766,7 @@ function App() {
                 onPause={() => pauseDownload(download.id)}
                 onResume={() => resumeDownload(download.id)}
                 onCancel={() => cancelDownload(download.id)}
+                onStop={() => stopRecording(download.id)}
               />
             ))}
           </section>
@@ -681,7 +821,7 @@ function App() {
         )}
 
         {/* Empty State */}
-        {activeDownloads.length === 0 && completedDownloads.length === 0 && interruptedDownloads.length === 0 && !urlInfo && !videoInfo && !showSettings && !showHistory && (
+        {activeDownloads.length === 0 && completedDownloads.length === 0 && interruptedDownloads.length === 0 && !urlInfo && !videoInfo && !playlistInfo && !showSettings && !showHistory && (
           <div className="text-center py-16">
             <svg className="w-20 h-20 mx-auto mb-4" viewBox="0 0 1024 1024" fill="none" xmlns="http://www.w3.org/2000/svg">
               <defs>
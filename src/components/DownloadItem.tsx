@@ -6,6 +6,7 @@ interface DownloadItemProps {
   onPause: () => void;
   onResume: () => void;
   onCancel: () => void;
+  onStop?: () => void; // live recordings only
 }
 
 function formatEta(seconds: number | null | undefined): string {
@@ -18,7 +19,15 @@ function formatEta(seconds: number | null | undefined): string {
   return `${s}s`;
 }
 
-export function DownloadItem({ download, onPause, onResume, onCancel }: DownloadItemProps) {
+function formatElapsed(seconds: number): string {
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = Math.floor(seconds % 60);
+  const pad = (n: number) => n.toString().padStart(2, "0");
+  return h > 0 ? `${h}:${pad(m)}:${pad(s)}` : `${m}:${pad(s)}`;
+}
+
+export function DownloadItem({ download, onPause, onResume, onCancel, onStop }: DownloadItemProps) {
   const isVideo = download.type === 'video';
   // Use progress directly if it's a number (for video/unified), or calculate from downloaded/total
   const percent = typeof download.progress === 'number' 
@@ -30,6 +39,8 @@ export function DownloadItem({ download, onPause, onResume, onCancel }: Download
   const isPaused = download.status === "paused";
   const isMerging = download.status === "merging";
   const isStarting = download.status === "starting";
+  // A live recording has no known end, so show time and size instead of a percentage
+  const isRecording = download.status === "recording";
 
   return (
     <div className="card card-hover">
@@ -91,6 +102,15 @@ export function DownloadItem({ download, onPause, onResume, onCancel }: Download
             </>
           )}
           
+          {isRecording && onStop && (
+            <button onClick={onStop} className="btn-warning text-xs sm:text-sm px-2 sm:px-3" title="Stop recording and keep the file">
+              <svg className="w-3.5 h-3.5 sm:w-4 sm:h-4 sm:mr-1 inline-block" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <rect x="6" y="6" width="12" height="12" rx="1" strokeWidth={2} />
+              </svg>
+              <span className="hidden sm:inline">Stop</span>
+            </button>
+          )}
+
           <button onClick={onCancel} className="btn-danger text-xs sm:text-sm px-2 sm:px-3" title="Cancel">
             <svg className="w-3.5 h-3.5 sm:w-4 sm:h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
               <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M6 18L18 6M6 6l12 12" />
@@ -104,12 +124,12 @@ export function DownloadItem({ download, onPause, onResume, onCancel }: Download
         <div className="relative">
           <div className="progress-bar">
             <div
-              className={`progress-fill ${isStarting ? 'animate-pulse' : ''}`}
-              style={{ width: isStarting ? '100%' : `${percent}%`, opacity: isStarting ? 0.3 : 1 }}
+              className={`progress-fill ${isStarting || isRecording ? 'animate-pulse' : ''}`}
+              style={{ width: isStarting || isRecording ? '100%' : `${percent}%`, opacity: isStarting ? 0.3 : 1 }}
             />
           </div>
           {/* Glow effect when downloading */}
-          {!isPaused && !isStarting && (
+          {!isPaused && !isStarting && !isRecording && (
             <div
               className="absolute top-0 left-0 h-full rounded-full bg-accent/20 blur-sm transition-all duration-150"
               style={{ width: `${percent}%` }}
@@ -135,6 +155,12 @@ export function DownloadItem({ download, onPause, onResume, onCancel }: Download
               </svg>
               Merging video and audio...
             </span>
+          ) : isRecording ? (
+            <div className="flex items-center gap-2 sm:gap-4">
+              <span className="badge badge-danger">REC</span>
+              <span className="text-accent font-medium">{formatElapsed(download.elapsed || 0)}</span>
+              <span className="text-gray-400">{formatBytes(download.downloaded)}</span>
+            </div>
           ) : (
              <div className="flex items-center gap-2 sm:gap-4">
               <span className="text-gray-400">
@@ -173,6 +199,16 @@ export function DownloadItem({ download, onPause, onResume, onCancel }: Download
           </div>
         </div>
 
+        {/* Latest yt-dlp warning (Videos only) */}
+        {isVideo && download.warnings && download.warnings.length > 0 && (
+          <p
+            className="mt-2 text-xs text-yellow-400 truncate"
+            title={download.warnings.join("\n")}
+          >
+            {download.warnings[download.warnings.length - 1]}
+          </p>
+        )}
+
         {/* Chunk Progress (Files only) */}
         {!isVideo && (download as any).progress?.chunk_progress?.length > 1 && (
           <div className="mt-2 sm:mt-3 pt-2 sm:pt-3 border-t border-dark-600">
@@ -1,5 +1,5 @@
 import { DownloadInfo } from "../types";
-import { formatBytes } from "../utils";
+import { formatBytes, thumbnailSrc } from "../utils";
 import { invoke } from "@tauri-apps/api/core";
 
 interface HistoryPanelProps {
@@ -29,6 +29,14 @@ export function HistoryPanel({ history, clearHistory, removeFromHistory }: Histo
     }
   }
 
+  async function openLog(id: string) {
+    try {
+      await invoke("open_download_log", { id });
+    } catch (e) {
+      console.error("Failed to open log:", e);
+    }
+  }
+
   function getStatusBadge(status: string) {
     switch (status) {
       case "Completed":
@@ -77,10 +85,20 @@ export function HistoryPanel({ history, clearHistory, removeFromHistory }: Histo
               key={item.id}
               className="flex items-center justify-between p-3 bg-dark-700/50 rounded-lg hover:bg-dark-700 transition-colors group"
             >
+              {item.thumbnail && (
+                <div className="w-12 h-8 mr-3 rounded overflow-hidden bg-dark-600 flex-shrink-0">
+                  <img src={thumbnailSrc(item.thumbnail)} alt="" className="w-full h-full object-cover" />
+                </div>
+              )}
               <div className="flex-1 min-w-0 mr-4">
-                <p className="font-medium text-gray-200 truncate text-sm">{item.filename}</p>
+                <p className="font-medium text-gray-200 truncate text-sm">
+                  {item.video_metadata?.title || item.filename}
+                </p>
                 <div className="flex items-center gap-2 mt-1">
                   <span className="text-xs text-gray-500">{formatBytes(item.total_size)}</span>
+                  {item.video_metadata?.uploader && (
+                    <span className="text-xs text-gray-500 truncate">{item.video_metadata.uploader}</span>
+                  )}
                   {getStatusBadge(item.status)}
                 </div>
               </div>
@@ -107,6 +125,17 @@ export function HistoryPanel({ history, clearHistory, removeFromHistory }: Histo
                     </button>
                   </>
                 )}
+                {item.is_video && (
+                  <button
+                    onClick={() => openLog(item.id)}
+                    className="p-2 text-gray-400 hover:text-accent hover:bg-dark-600 rounded-lg transition-colors"
+                    title="Open yt-dlp log"
+                  >
+                    <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                      <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M9 12h6m-6 4h6m2 5H7a2 2 0 01-2-2V5a2 2 0 012-2h5.586a1 1 0 01.707.293l5.414 5.414a1 1 0 01.293.707V19a2 2 0 01-2 2z" />
+                    </svg>
+                  </button>
+                )}
                 <button
                   onClick={() => removeFromHistory(item.id)}
                   className="p-2 text-gray-400 hover:text-red-400 hover:bg-dark-600 rounded-lg transition-colors"
@@ -0,0 +1,135 @@
+import { useState } from "react";
+import { FormatPreference, PlaylistInfo, PlaylistEntry } from "../types";
+
+interface PlaylistSelectProps {
+  info: PlaylistInfo;
+  onDownload: (entries: PlaylistEntry[], preference: FormatPreference) => void;
+  onCancel: () => void;
+}
+
+function preference(mode: FormatPreference["mode"], maxHeight: number | null): FormatPreference {
+  return { mode, max_height: maxHeight, video_codecs: [], audio_codecs: [], container: null, fallback: true };
+}
+
+// Playlists have no shared format list, so offer format preferences instead
+const QUALITY_OPTIONS = [
+  { value: "best", label: "Best Quality", preference: preference("video_audio", null) },
+  { value: "1080", label: "Up to 1080p", preference: preference("video_audio", 1080) },
+  { value: "720", label: "Up to 720p", preference: preference("video_audio", 720) },
+  { value: "480", label: "Up to 480p", preference: preference("video_audio", 480) },
+  { value: "audio", label: "Audio only", preference: preference("audio_only", null) },
+];
+
+function formatDuration(seconds: number | null): string {
+  if (!seconds) return "";
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = Math.floor(seconds % 60);
+  if (h > 0) {
+    return `${h}:${m.toString().padStart(2, "0")}:${s.toString().padStart(2, "0")}`;
+  }
+  return `${m}:${s.toString().padStart(2, "0")}`;
+}
+
+export function PlaylistSelect({ info, onDownload, onCancel }: PlaylistSelectProps) {
+  const [selected, setSelected] = useState<Set<number>>(
+    () => new Set(info.entries.map((entry) => entry.index))
+  );
+  const [quality, setQuality] = useState("best");
+
+  const allSelected = selected.size === info.entries.length;
+
+  function toggle(index: number) {
+    setSelected((prev) => {
+      const next = new Set(prev);
+      if (next.has(index)) {
+        next.delete(index);
+      } else {
+        next.add(index);
+      }
+      return next;
+    });
+  }
+
+  function toggleAll() {
+    setSelected(allSelected ? new Set() : new Set(info.entries.map((entry) => entry.index)));
+  }
+
+  return (
+    <div className="panel">
+      <div className="flex items-start justify-between gap-4">
+        <div className="min-w-0">
+          <h3 className="font-semibold text-gray-100 text-sm sm:text-base line-clamp-2 mb-1">
+            {info.title}
+          </h3>
+          <p className="text-xs sm:text-sm text-gray-400">
+            {info.uploader && `${info.uploader} · `}
+            {info.entries.length} {info.entries.length === 1 ? "video" : "videos"}
+          </p>
+        </div>
+        <button onClick={toggleAll} className="btn-ghost text-xs sm:text-sm flex-shrink-0">
+          {allSelected ? "Select none" : "Select all"}
+        </button>
+      </div>
+
+      {/* Entries */}
+      <div className="mt-4 max-h-72 overflow-y-auto space-y-1 pr-1">
+        {info.entries.map((entry) => (
+          <label
+            key={entry.index}
+            className="flex items-center gap-3 px-2 py-1.5 rounded-lg hover:bg-dark-700 cursor-pointer"
+          >
+            <input
+              type="checkbox"
+              checked={selected.has(entry.index)}
+              onChange={() => toggle(entry.index)}
+            />
+            <span className="text-xs text-gray-500 w-8 flex-shrink-0 text-right">{entry.index}</span>
+            <span className="flex-1 min-w-0 text-xs sm:text-sm text-gray-300 truncate">{entry.title}</span>
+            {entry.duration && (
+              <span className="text-xs text-gray-500 flex-shrink-0">{formatDuration(entry.duration)}</span>
+            )}
+          </label>
+        ))}
+      </div>
+
+      {/* Quality and actions */}
+      <div className="mt-4 pt-4 border-t border-dark-600">
+        <div className="flex flex-col sm:flex-row sm:items-center gap-3">
+          <select
+            value={quality}
+            onChange={(e) => setQuality(e.target.value)}
+            className="select flex-1 text-sm"
+          >
+            {QUALITY_OPTIONS.map((option) => (
+              <option key={option.value} value={option.value}>
+                {option.label}
+              </option>
+            ))}
+          </select>
+
+          <div className="flex gap-2">
+            <button onClick={onCancel} className="btn-ghost text-sm px-4">
+              Cancel
+            </button>
+            <button
+              onClick={() =>
+                onDownload(
+                  info.entries.filter((entry) => selected.has(entry.index)),
+                  (QUALITY_OPTIONS.find((option) => option.value === quality) ?? QUALITY_OPTIONS[0]).preference
+                )
+              }
+              disabled={selected.size === 0}
+              className="btn-primary text-sm px-4"
+            >
+              <svg className="w-4 h-4 mr-2" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M4 16v1a3 3 0 003 3h10a3 3 0 003-3v-1m-4-4l-4 4m0 0l-4-4m4 4V4" />
+              </svg>
+              Download {selected.size} {selected.size === 1 ? "video" : "videos"}
+            </button>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
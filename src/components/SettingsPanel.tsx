@@ -1,3 +1,6 @@
+import { useState, useEffect } from "react";
+import { RpcSettings } from "../types";
+
 interface SettingsPanelProps {
   connections: number;
   downloadFolder: string;
@@ -6,6 +9,8 @@ interface SettingsPanelProps {
   selectDownloadFolder: () => void;
   resetDownloadFolder: () => void;
   updateSpeedLimit: (limit: number) => void;
+  rpcSettings: RpcSettings | null;
+  updateRpcSettings: (rpc: RpcSettings) => void;
 }
 
 export function SettingsPanel({
@@ -16,7 +21,27 @@ export function SettingsPanel({
   selectDownloadFolder,
   resetDownloadFolder,
   updateSpeedLimit,
+  rpcSettings,
+  updateRpcSettings,
 }: SettingsPanelProps) {
+  const [rpcPort, setRpcPort] = useState("");
+  const [rpcSecret, setRpcSecret] = useState("");
+
+  useEffect(() => {
+    if (rpcSettings) {
+      setRpcPort(String(rpcSettings.port));
+      setRpcSecret(rpcSettings.secret);
+    }
+  }, [rpcSettings]);
+
+  const rpcChanged =
+    rpcSettings !== null && (rpcPort !== String(rpcSettings.port) || rpcSecret !== rpcSettings.secret);
+
+  function applyRpc(enabled: boolean) {
+    if (!rpcSettings) return;
+    updateRpcSettings({ enabled, port: Number(rpcPort) || 0, secret: rpcSecret });
+  }
+
   return (
     <div className="panel">
       <div className="flex items-center gap-2 sm:gap-3 mb-4">
@@ -103,6 +128,61 @@ export function SettingsPanel({
             </div>
           </div>
         </div>
+
+        {/* JSON-RPC server */}
+        {rpcSettings && (
+          <div className="space-y-2">
+            <div className="flex flex-col sm:flex-row sm:items-center justify-between gap-2 sm:gap-4">
+              <div className="flex items-center gap-2 sm:gap-3">
+                <svg className="w-4 h-4 text-gray-500 flex-shrink-0" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                  <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M8 9l3 3-3 3m5 0h3M5 20h14a2 2 0 002-2V6a2 2 0 00-2-2H5a2 2 0 00-2 2v12a2 2 0 002 2z" />
+                </svg>
+                <div>
+                  <label className="text-xs sm:text-sm text-gray-300">JSON-RPC server</label>
+                  <p className="text-xs text-gray-500 hidden sm:block">Accept downloads from aria2 clients and the browser extension</p>
+                </div>
+              </div>
+              <label className="flex items-center gap-2 text-xs sm:text-sm text-gray-300 flex-shrink-0">
+                <input
+                  type="checkbox"
+                  checked={rpcSettings.enabled}
+                  onChange={(e) => applyRpc(e.target.checked)}
+                />
+                Enabled
+              </label>
+            </div>
+            <div className="flex flex-col sm:flex-row gap-2">
+              <input
+                type="number"
+                min={1}
+                max={65535}
+                value={rpcPort}
+                onChange={(e) => setRpcPort(e.target.value)}
+                className="input text-xs sm:text-sm sm:w-28"
+                placeholder="Port"
+              />
+              <input
+                type="text"
+                value={rpcSecret}
+                onChange={(e) => setRpcSecret(e.target.value)}
+                className="input flex-1 text-xs sm:text-sm font-mono min-w-0"
+                placeholder="Secret (generated when empty)"
+              />
+              <button
+                onClick={() => applyRpc(rpcSettings.enabled)}
+                disabled={!rpcChanged}
+                className="btn-secondary text-xs sm:text-sm flex-shrink-0"
+              >
+                Apply
+              </button>
+            </div>
+            {rpcSettings.enabled && (
+              <p className="text-xs text-gray-500 break-all">
+                http://127.0.0.1:{rpcSettings.port}/jsonrpc, token:{rpcSettings.secret}
+              </p>
+            )}
+          </div>
+        )}
       </div>
     </div>
   );
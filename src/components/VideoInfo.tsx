@@ -1,13 +1,32 @@
 import { useState } from "react";
-import { VideoInfo as VideoInfoType, VideoFormat } from "../types";
+import { invoke } from "@tauri-apps/api/core";
+import {
+  VideoInfo as VideoInfoType,
+  VideoFormat,
+  FormatPreference,
+  FormatPreview,
+  VideoDownloadRequest,
+  ChapterOptions,
+  TimeRange,
+  LiveOptions,
+} from "../types";
 import { formatBytes } from "../utils";
 
 interface VideoInfoProps {
   info: VideoInfoType;
-  onDownload: (formatId: string) => void;
+  onDownload: (formatId: string, request?: VideoDownloadRequest | null) => void;
   onCancel: () => void;
 }
 
+const DEFAULT_PREFERENCE: FormatPreference = {
+  mode: "video_audio",
+  max_height: null,
+  video_codecs: [],
+  audio_codecs: [],
+  container: null,
+  fallback: true,
+};
+
 function formatDuration(seconds: number | null): string {
   if (!seconds) return "Unknown";
   const h = Math.floor(seconds / 3600);
@@ -19,6 +38,27 @@ function formatDuration(seconds: number | null): string {
   return `${m}:${s.toString().padStart(2, "0")}`;
 }
 
+// SponsorBlock categories that can be cut out; the rest only mark points
+const SPONSORBLOCK_CATEGORIES = [
+  { value: "sponsor", label: "Sponsor" },
+  { value: "intro", label: "Intro" },
+  { value: "outro", label: "Outro" },
+  { value: "selfpromo", label: "Self-promotion" },
+  { value: "preview", label: "Preview" },
+  { value: "filler", label: "Filler" },
+  { value: "interaction", label: "Interaction reminder" },
+  { value: "music_offtopic", label: "Non-music" },
+];
+
+type SponsorAction = "keep" | "mark" | "remove";
+
+/** Parse "90", "1:30" or "1:01:30" into seconds */
+function parseTimestamp(value: string): number | null {
+  const parts = value.trim().split(":");
+  if (parts.length > 3 || parts.some((p) => p === "" || isNaN(Number(p)))) return null;
+  return parts.reduce((total, part) => total * 60 + Number(part), 0);
+}
+
 function formatViews(count: number | null): string {
   if (!count) return "";
   if (count >= 1_000_000) {
@@ -52,6 +92,99 @@ function getFormatSize(format: VideoFormat): string {
 
 export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProps) {
   const [selectedFormat, setSelectedFormat] = useState(info.best_format || "best");
+  const [usePreference, setUsePreference] = useState(false);
+  const [preference, setPreference] = useState<FormatPreference>(DEFAULT_PREFERENCE);
+  const [preview, setPreview] = useState<FormatPreview | null>(null);
+  const [previewing, setPreviewing] = useState(false);
+  const [previewError, setPreviewError] = useState<string | null>(null);
+
+  function updatePreference(changes: Partial<FormatPreference>) {
+    setPreference((prev) => ({ ...prev, ...changes }));
+    // The old preview no longer matches
+    setPreview(null);
+    setPreviewError(null);
+  }
+
+  async function previewSelection() {
+    setPreviewing(true);
+    setPreviewError(null);
+    try {
+      const result = await invoke<FormatPreview>("preview_format_selection", {
+        url: info.url,
+        preference,
+      });
+      setPreview(result);
+    } catch (e) {
+      setPreview(null);
+      setPreviewError(String(e));
+    } finally {
+      setPreviewing(false);
+    }
+  }
+
+  const [showOptions, setShowOptions] = useState(false);
+  const [splitChapters, setSplitChapters] = useState(false);
+  const [sections, setSections] = useState<{ start: string; end: string }[]>([]);
+  const [sponsorActions, setSponsorActions] = useState<Record<string, SponsorAction>>({});
+  const [optionsError, setOptionsError] = useState<string | null>(null);
+  const [fromStart, setFromStart] = useState(false);
+
+  const isLive = info.live_status === "is_live" || info.live_status === "is_upcoming";
+
+  function updateSection(index: number, field: "start" | "end", value: string) {
+    setSections((prev) => prev.map((s, i) => (i === index ? { ...s, [field]: value } : s)));
+  }
+
+  function chapterOptions(): ChapterOptions | null {
+    const ranges: TimeRange[] = [];
+    for (const section of sections) {
+      const start = parseTimestamp(section.start);
+      const end = parseTimestamp(section.end);
+      if (start === null || end === null || end <= start) {
+        throw new Error(`Invalid section: ${section.start || "?"} - ${section.end || "?"}`);
+      }
+      ranges.push({ start, end });
+    }
+    const categories = (action: SponsorAction) =>
+      Object.keys(sponsorActions).filter((c) => sponsorActions[c] === action);
+
+    const options: ChapterOptions = {
+      split: splitChapters,
+      sections: ranges,
+      sponsorblock_mark: categories("mark"),
+      sponsorblock_remove: categories("remove"),
+    };
+    const empty =
+      !options.split &&
+      options.sections.length === 0 &&
+      options.sponsorblock_mark.length === 0 &&
+      options.sponsorblock_remove.length === 0;
+    return empty ? null : options;
+  }
+
+  function download() {
+    let chapters: ChapterOptions | null;
+    try {
+      chapters = chapterOptions();
+    } catch (e) {
+      setOptionsError(e instanceof Error ? e.message : String(e));
+      setShowOptions(true);
+      return;
+    }
+    setOptionsError(null);
+
+    // Streams are recorded until they end or are stopped; scheduled ones are waited for
+    const live: LiveOptions | null = isLive
+      ? { from_start: fromStart, wait_for_video: info.live_status === "is_upcoming" ? 60 : null }
+      : null;
+
+    if (usePreference) {
+      // The backend turns the preference into -f/-S, so the format id is only a placeholder
+      onDownload("best", { format_preference: preference, chapters, live });
+    } else {
+      onDownload(selectedFormat, { chapters, live });
+    }
+  }
 
   return (
     <div className="panel">
@@ -102,6 +235,121 @@ export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProp
         </div>
       </div>
 
+      {/* Live stream */}
+      {isLive && (
+        <div className="mt-4 flex flex-wrap items-center gap-3 text-xs sm:text-sm">
+          <span className="px-2 py-0.5 rounded bg-red-500/20 text-red-400 font-medium">
+            {info.live_status === "is_upcoming" ? "Upcoming stream" : "Live"}
+          </span>
+          <label className="flex items-center gap-2 text-gray-300">
+            <input type="checkbox" checked={fromStart} onChange={(e) => setFromStart(e.target.checked)} />
+            Record from the start of the stream
+          </label>
+        </div>
+      )}
+
+      {/* Chapters */}
+      {info.chapters.length > 0 && (
+        <details className="mt-4 text-xs sm:text-sm">
+          <summary className="cursor-pointer text-gray-400">
+            {info.chapters.length} {info.chapters.length === 1 ? "chapter" : "chapters"}
+          </summary>
+          <ol className="mt-2 max-h-48 overflow-y-auto space-y-1">
+            {info.chapters.map((chapter, i) => (
+              <li key={i} className="flex gap-3 text-gray-300">
+                <span className="text-gray-500 w-16 flex-shrink-0 text-right">
+                  {chapter.start_time > 0 ? formatDuration(chapter.start_time) : "0:00"}
+                </span>
+                <span className="truncate">{chapter.title}</span>
+              </li>
+            ))}
+          </ol>
+        </details>
+      )}
+
+      {/* Chapters, sections and SponsorBlock */}
+      <div className="mt-4">
+        <button onClick={() => setShowOptions(!showOptions)} className="btn-ghost text-xs sm:text-sm">
+          {showOptions ? "Hide" : "Show"} chapter and SponsorBlock options
+        </button>
+
+        {showOptions && (
+          <div className="mt-3 space-y-4">
+            {info.chapters.length > 0 && (
+              <label className="flex items-center gap-2 text-xs sm:text-sm text-gray-300">
+                <input
+                  type="checkbox"
+                  checked={splitChapters}
+                  onChange={(e) => setSplitChapters(e.target.checked)}
+                />
+                Also save each chapter as its own file
+              </label>
+            )}
+
+            <div className="space-y-2">
+              <div className="flex items-center justify-between">
+                <span className="text-xs sm:text-sm text-gray-300">Download only these parts</span>
+                <button
+                  onClick={() => setSections([...sections, { start: "", end: "" }])}
+                  className="btn-ghost text-xs"
+                >
+                  Add section
+                </button>
+              </div>
+              {sections.map((section, i) => (
+                <div key={i} className="flex items-center gap-2">
+                  <input
+                    type="text"
+                    value={section.start}
+                    onChange={(e) => updateSection(i, "start", e.target.value)}
+                    className="input text-xs sm:text-sm flex-1 min-w-0"
+                    placeholder="Start (1:30)"
+                  />
+                  <span className="text-gray-500">-</span>
+                  <input
+                    type="text"
+                    value={section.end}
+                    onChange={(e) => updateSection(i, "end", e.target.value)}
+                    className="input text-xs sm:text-sm flex-1 min-w-0"
+                    placeholder="End (2:45)"
+                  />
+                  <button
+                    onClick={() => setSections(sections.filter((_, j) => j !== i))}
+                    className="btn-ghost text-xs"
+                  >
+                    Remove
+                  </button>
+                </div>
+              ))}
+            </div>
+
+            <div className="space-y-2">
+              <span className="text-xs sm:text-sm text-gray-300">SponsorBlock</span>
+              <div className="grid grid-cols-1 sm:grid-cols-2 gap-2">
+                {SPONSORBLOCK_CATEGORIES.map((category) => (
+                  <div key={category.value} className="flex items-center justify-between gap-2">
+                    <span className="text-xs text-gray-400">{category.label}</span>
+                    <select
+                      value={sponsorActions[category.value] ?? "keep"}
+                      onChange={(e) =>
+                        setSponsorActions({ ...sponsorActions, [category.value]: e.target.value as SponsorAction })
+                      }
+                      className="select text-xs"
+                    >
+                      <option value="keep">Keep</option>
+                      <option value="mark">Mark as chapter</option>
+                      <option value="remove">Cut out</option>
+                    </select>
+                  </div>
+                ))}
+              </div>
+            </div>
+
+            {optionsError && <p className="text-xs text-red-400">{optionsError}</p>}
+          </div>
+        )}
+      </div>
+
       {/* Format Selector */}
       <div className="mt-4 pt-4 border-t border-dark-600">
         <div className="flex flex-col sm:flex-row sm:items-center gap-3">
@@ -110,10 +358,12 @@ export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProp
               <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M7 4v16M17 4v16M3 8h4m10 0h4M3 12h18M3 16h4m10 0h4M4 20h16a1 1 0 001-1V5a1 1 0 00-1-1H4a1 1 0 00-1 1v14a1 1 0 001 1z" />
             </svg>
             <select
-              value={selectedFormat}
+              value={usePreference ? "" : selectedFormat}
               onChange={(e) => setSelectedFormat(e.target.value)}
+              disabled={usePreference}
               className="select flex-1 text-sm"
             >
+              {usePreference && <option value="">By preference</option>}
               {info.formats.map((format) => (
                 <option key={format.format_id} value={format.format_id}>
                   {getFormatLabel(format)}
@@ -121,6 +371,14 @@ export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProp
                 </option>
               ))}
             </select>
+            <label className="flex items-center gap-1.5 text-xs text-gray-400 flex-shrink-0">
+              <input
+                type="checkbox"
+                checked={usePreference}
+                onChange={(e) => setUsePreference(e.target.checked)}
+              />
+              By preference
+            </label>
           </div>
 
           <div className="flex gap-2">
@@ -131,7 +389,7 @@ export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProp
               Cancel
             </button>
             <button
-              onClick={() => onDownload(selectedFormat)}
+              onClick={download}
               className="btn-primary text-sm px-4"
             >
               <svg className="w-4 h-4 mr-2" fill="none" stroke="currentColor" viewBox="0 0 24 24">
@@ -141,6 +399,109 @@ export function VideoInfoComponent({ info, onDownload, onCancel }: VideoInfoProp
             </button>
           </div>
         </div>
+
+        {usePreference && (
+          <div className="mt-3 space-y-3">
+            <div className="grid grid-cols-2 sm:grid-cols-5 gap-2">
+              <select
+                value={preference.mode}
+                onChange={(e) => updatePreference({ mode: e.target.value as FormatPreference["mode"] })}
+                className="select text-xs sm:text-sm"
+              >
+                <option value="video_audio">Video + audio</option>
+                <option value="combined">Single file</option>
+                <option value="video_only">Video only</option>
+                <option value="audio_only">Audio only</option>
+              </select>
+              <select
+                value={preference.max_height ?? 0}
+                onChange={(e) => updatePreference({ max_height: Number(e.target.value) || null })}
+                disabled={preference.mode === "audio_only"}
+                className="select text-xs sm:text-sm"
+              >
+                <option value={0}>Any height</option>
+                {[2160, 1440, 1080, 720, 480, 360].map((h) => (
+                  <option key={h} value={h}>
+                    Up to {h}p
+                  </option>
+                ))}
+              </select>
+              <select
+                value={preference.video_codecs[0] ?? ""}
+                onChange={(e) => updatePreference({ video_codecs: e.target.value ? [e.target.value] : [] })}
+                disabled={preference.mode === "audio_only"}
+                className="select text-xs sm:text-sm"
+              >
+                <option value="">Any video codec</option>
+                <option value="av1">AV1</option>
+                <option value="vp9">VP9</option>
+                <option value="h265">H.265</option>
+                <option value="h264">H.264</option>
+              </select>
+              <select
+                value={preference.audio_codecs[0] ?? ""}
+                onChange={(e) => updatePreference({ audio_codecs: e.target.value ? [e.target.value] : [] })}
+                disabled={preference.mode === "video_only"}
+                className="select text-xs sm:text-sm"
+              >
+                <option value="">Any audio codec</option>
+                <option value="opus">Opus</option>
+                <option value="aac">AAC</option>
+                <option value="vorbis">Vorbis</option>
+                <option value="mp3">MP3</option>
+              </select>
+              <select
+                value={preference.container ?? ""}
+                onChange={(e) => updatePreference({ container: e.target.value || null })}
+                className="select text-xs sm:text-sm"
+              >
+                <option value="">Any container</option>
+                {["mp4", "webm", "mkv", "m4a", "mp3", "opus"].map((c) => (
+                  <option key={c} value={c}>
+                    {c}
+                  </option>
+                ))}
+              </select>
+            </div>
+
+            <div className="flex flex-wrap items-center gap-3">
+              <label className="flex items-center gap-1.5 text-xs text-gray-400">
+                <input
+                  type="checkbox"
+                  checked={preference.fallback}
+                  onChange={(e) => updatePreference({ fallback: e.target.checked })}
+                />
+                Fall back to the best available format
+              </label>
+              <button onClick={previewSelection} disabled={previewing} className="btn-secondary text-xs sm:text-sm">
+                {previewing ? "Checking..." : "Preview"}
+              </button>
+            </div>
+
+            {previewError && <p className="text-xs text-red-400 break-words">{previewError}</p>}
+            {preview && (
+              <div className="px-3 py-2 bg-dark-700 rounded-lg border border-dark-600 space-y-1">
+                {preview.formats.length > 0 ? (
+                  preview.formats.map((format) => (
+                    <p key={format.format_id} className="text-xs sm:text-sm text-gray-300">
+                      {getFormatLabel(format)}
+                      {format.ext && ` · ${format.ext}`}
+                      {format.vcodec && format.vcodec !== "none" && ` · ${format.vcodec}`}
+                      {format.acodec && format.acodec !== "none" && ` · ${format.acodec}`}
+                      {getFormatSize(format) && ` · ${getFormatSize(format)}`}
+                    </p>
+                  ))
+                ) : (
+                  <p className="text-xs sm:text-sm text-gray-400">No format matches this preference</p>
+                )}
+                <p className="text-xs text-gray-500 font-mono break-all">
+                  -f {preview.selector.format}
+                  {preview.selector.sort.length > 0 && ` -S ${preview.selector.sort.join(",")}`}
+                </p>
+              </div>
+            )}
+          </div>
+        )}
       </div>
     </div>
   );
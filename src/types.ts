@@ -6,15 +6,17 @@ export interface DownloadItem {
   downloaded: number;
   speed: number;
   progress: number;
-  status: 'downloading' | 'paused' | 'error' | 'completed' | 'cancelled' | 'merging' | 'starting';
+  status: 'downloading' | 'paused' | 'error' | 'completed' | 'cancelled' | 'merging' | 'starting' | 'recording' | 'waiting';
   created_at: number;
   // Video specific
   type?: 'file' | 'video';
   thumbnail?: string | null;
   videoTitle?: string;
   eta?: number | null;
+  elapsed?: number | null; // seconds recorded, for live streams
   completedPath?: string; // Add this as it is used in App.tsx
   error?: string; // Add this as it is used in App.tsx
+  warnings?: string[]; // yt-dlp warnings seen while downloading
 }
 
 export interface ChunkProgress {
@@ -33,6 +35,7 @@ export interface DownloadProgress {
   // Optional extras from video downloader
   eta?: number;
   percent?: number;
+  elapsed?: number | null; // seconds recorded, for live streams
 }
 
 export interface DownloadComplete {
@@ -47,6 +50,11 @@ export interface DownloadError {
   error: string;
 }
 
+export interface VideoWarning {
+  id: string;
+  message: string;
+}
+
 export interface UrlInfo {
   url: string;
   filename: string;
@@ -81,9 +89,66 @@ export interface DownloadInfo {
   downloaded: number;
   status: string;
   resumable: boolean;
+  is_video: boolean;
+  parent_id: string | null;
+  is_playlist: boolean;
+  subtitle_files: string[];
+  thumbnail: string | null; // remote URL, or a local path once cached
+  video_metadata: VideoMetadata | null;
   created_at: number;
 }
 
+export interface VideoMetadata {
+  id: string | null;
+  title: string | null;
+  uploader: string | null;
+  duration: number | null;
+  ext: string | null;
+  // Only known when an info JSON was written
+  channel: string | null;
+  upload_date: string | null; // YYYYMMDD
+  tags: string[];
+  webpage_url: string | null;
+  info_json: string | null;
+}
+
+export interface EmbedOptions {
+  metadata: boolean;
+  thumbnail: boolean;
+  chapters: boolean;
+  info_json: boolean; // write a .info.json next to the file
+}
+
+// aria2-compatible JSON-RPC server on 127.0.0.1
+export interface RpcSettings {
+  enabled: boolean;
+  port: number;
+  secret: string; // clients send "token:<secret>" as the first param
+}
+
+export interface HistoryQuery {
+  statuses?: string[];
+  kind?: 'file' | 'video';
+  created_after?: number;
+  created_before?: number;
+  host?: string;
+  filename?: string;
+  uploader?: string;
+  title?: string; // video title, or filename when there is none
+  min_size?: number;
+  max_size?: number;
+  sort_by?: 'created_at' | 'updated_at' | 'filename' | 'size' | 'status';
+  order?: 'asc' | 'desc';
+  offset?: number;
+  limit?: number;
+}
+
+export interface HistoryPage {
+  downloads: DownloadInfo[];
+  total: number;
+  offset: number;
+}
+
 // Video types
 export interface VideoFormat {
   format_id: string;
@@ -107,4 +172,151 @@ export interface VideoInfo {
   view_count: number | null;
   formats: VideoFormat[];
   best_format: string | null;
-}
\ No newline at end of file
+  subtitles: SubtitleTrack[];
+  chapters: Chapter[];
+  live_status: string | null; // "is_live", "is_upcoming", "was_live", ...
+}
+
+export interface Chapter {
+  title: string;
+  start_time: number; // seconds
+  end_time: number;
+}
+
+export interface TimeRange {
+  start: number; // seconds
+  end: number;
+}
+
+export interface ChapterOptions {
+  split: boolean; // also write each chapter to its own file
+  sections: TimeRange[]; // download only these parts
+  sponsorblock_mark: string[];
+  sponsorblock_remove: string[];
+}
+
+export interface SubtitleTrack {
+  lang: string;
+  name: string | null;
+  formats: string[];
+  automatic: boolean;
+}
+
+export interface AudioOptions {
+  format: "mp3" | "m4a" | "opus";
+  quality: string | null;
+  embed_thumbnail: boolean;
+  embed_metadata: boolean;
+}
+
+export interface SubtitleOptions {
+  languages: string[];
+  format: "srt" | "vtt";
+  embed: boolean;
+  include_automatic: boolean;
+}
+export interface ImportSummary {
+  imported: number;
+  duplicates: number;
+  invalid: number;
+  queued: string[];
+}
+
+export interface PlaylistEntry {
+  index: number;
+  id: string;
+  url: string;
+  title: string;
+  duration: number | null;
+  uploader: string | null;
+}
+
+export interface PlaylistInfo {
+  url: string;
+  id: string | null;
+  title: string;
+  uploader: string | null;
+  entries: PlaylistEntry[];
+}
+
+export interface FfmpegInfo {
+  path: string;
+  source: "custom" | "managed" | "system";
+  version: string;
+  capabilities: {
+    mp3: boolean;
+    aac: boolean;
+    opus: boolean;
+    ffprobe: boolean;
+  };
+}
+
+export interface YtdlpUpdate {
+  previous: string | null;
+  current: string;
+  updated: boolean;
+}
+
+export interface YtdlpUpdateCheck {
+  current: string | null;
+  latest: string;
+  update_available: boolean;
+}
+
+export interface YtdlpUpdateSettings {
+  channel: "stable" | "nightly";
+  pinned_version: string | null;
+  auto_update: boolean;
+  interval_hours: number;
+  last_check: number;
+}
+
+export type CookieSource =
+  | { type: "file"; path: string }
+  | { type: "browser"; browser: string; profile: string | null };
+
+export interface SiteCookies {
+  domain: string;
+  source: CookieSource;
+}
+
+export interface VideoDetectionSettings {
+  patterns: string[]; // regexes matched against the whole URL
+  probe_ytdlp: boolean; // ask yt-dlp about URLs no pattern matches
+}
+
+export interface FormatPreference {
+  mode: "video_audio" | "combined" | "video_only" | "audio_only";
+  max_height: number | null;
+  video_codecs: string[];
+  audio_codecs: string[];
+  container: string | null;
+  fallback: boolean;
+}
+
+export interface FormatSelector {
+  format: string;
+  sort: string[];
+  merge_output_format: string | null;
+}
+
+export interface FormatPreview {
+  selector: FormatSelector;
+  formats: VideoFormat[];
+}
+
+export interface VideoDownloadRequest {
+  subtitles?: SubtitleOptions | null;
+  audio?: AudioOptions | null;
+  format_preference?: FormatPreference | null;
+  output_template?: string | null;
+  live?: LiveOptions | null;
+  chapters?: ChapterOptions | null;
+  embed?: EmbedOptions | null; // overrides the Settings default
+  info?: VideoInfo | null; // saves the backend a second lookup
+}
+
+export interface LiveOptions {
+  from_start: boolean;
+  wait_for_video: number | null;
+}
@@ -1,3 +1,5 @@
+import { convertFileSrc } from "@tauri-apps/api/core";
+
 export function formatBytes(bytes: number): string {
   if (bytes < 1024) return bytes + " B";
   if (bytes < 1024 * 1024) return (bytes / 1024).toFixed(2) + " KB";
@@ -11,3 +13,8 @@ export function formatSpeed(bytesPerSec: number): string {
   if (bytesPerSec < 1024 * 1024 * 1024) return (bytesPerSec / (1024 * 1024)).toFixed(2) + " MB/s";
   return (bytesPerSec / (1024 * 1024 * 1024)).toFixed(2) + " GB/s";
 }
+
+// Thumbnails are remote URLs until the backend caches them on disk
+export function thumbnailSrc(thumbnail: string): string {
+  return /^https?:\/\//.test(thumbnail) ? thumbnail : convertFileSrc(thumbnail);
+}